@@ -0,0 +1,205 @@
+//! The derive input, with its `#[json(...)]` attributes read and checked.
+
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Error, ExprPath, Fields, Generics, Ident, LitStr, Member, Result, Type};
+
+pub struct Container<'a> {
+    pub ident: &'a Ident,
+    pub generics: &'a Generics,
+    pub deny_unknown_fields: bool,
+    pub tagging: Tagging,
+    pub body: Body<'a>,
+}
+
+pub enum Tagging {
+    /// `{"Variant": payload}`, or `"Variant"` for unit variants.
+    External,
+    /// `{"tag": "Variant", ...fields}`.
+    Internal { tag: String },
+    /// `{"tag": "Variant", "content": payload}`.
+    Adjacent { tag: String, content: String },
+    /// The payload alone.
+    Untagged,
+}
+
+pub enum Body<'a> {
+    Struct(Style, Vec<Field<'a>>),
+    Enum(Vec<Variant<'a>>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Named,
+    Tuple,
+    Unit,
+}
+
+pub struct Variant<'a> {
+    pub ident: &'a Ident,
+    pub name: String,
+    pub style: Style,
+    pub fields: Vec<Field<'a>>,
+}
+
+pub struct Field<'a> {
+    pub member: Member,
+    pub ty: &'a Type,
+    pub name: String,
+    pub skip: bool,
+    pub flatten: bool,
+    pub default: Option<Default>,
+}
+
+pub enum Default {
+    Trait,
+    Path(ExprPath),
+}
+
+impl<'a> Container<'a> {
+    pub fn from_input(input: &'a DeriveInput) -> Result<Self> {
+        let mut deny_unknown_fields = false;
+        let mut tag = None;
+        let mut content = None;
+        let mut untagged = false;
+        for attr in json_attrs(&input.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("deny_unknown_fields") {
+                    deny_unknown_fields = true;
+                } else if meta.path.is_ident("tag") {
+                    tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") {
+                    content = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("untagged") {
+                    untagged = true;
+                } else {
+                    return Err(meta.error("unknown container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let tagging = match (tag, content, untagged) {
+            (None, None, false) => Tagging::External,
+            (Some(tag), None, false) => Tagging::Internal { tag },
+            (Some(tag), Some(content), false) => Tagging::Adjacent { tag, content },
+            (None, None, true) => Tagging::Untagged,
+            (None, Some(_), false) => return Err(Error::new_spanned(input, "`content` requires `tag`")),
+            _ => return Err(Error::new_spanned(input, "`untagged` cannot be combined with `tag`")),
+        };
+
+        let body = match &input.data {
+            Data::Struct(data) => {
+                if !matches!(tagging, Tagging::External) {
+                    return Err(Error::new_spanned(input, "enum tagging attributes only apply to enums"));
+                }
+                let (style, fields) = fields(&data.fields)?;
+                Body::Struct(style, fields)
+            }
+            Data::Enum(data) => {
+                let mut variants = Vec::new();
+                for variant in &data.variants {
+                    let (style, fields) = fields(&variant.fields)?;
+                    if matches!(tagging, Tagging::Internal { .. }) && style == Style::Tuple {
+                        return Err(Error::new_spanned(variant, "internally tagged enums only support unit and struct variants"));
+                    }
+                    variants.push(Variant {
+                        ident: &variant.ident,
+                        name: rename(&variant.attrs)?.unwrap_or_else(|| variant.ident.to_string()),
+                        style,
+                        fields,
+                    });
+                }
+                Body::Enum(variants)
+            }
+            Data::Union(_) => return Err(Error::new_spanned(input, "unions are not supported")),
+        };
+
+        let container = Container { ident: &input.ident, generics: &input.generics, deny_unknown_fields, tagging, body };
+        container.check_flatten()?;
+        Ok(container)
+    }
+
+    // Flattened fields take whatever members are left, so every member is
+    // known to someone.
+    fn check_flatten(&self) -> Result<()> {
+        if !self.deny_unknown_fields {
+            return Ok(());
+        }
+        let fields: Vec<&Field> = match &self.body {
+            Body::Struct(_, fields) => fields.iter().collect(),
+            Body::Enum(variants) => variants.iter().flat_map(|v| &v.fields).collect(),
+        };
+        match fields.iter().find(|f| f.flatten) {
+            Some(field) => Err(Error::new(field.ty.span(), "`flatten` cannot be combined with `deny_unknown_fields`")),
+            None => Ok(()),
+        }
+    }
+}
+
+fn json_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("json"))
+}
+
+fn rename(attrs: &[Attribute]) -> Result<Option<String>> {
+    let mut name = None;
+    for attr in json_attrs(attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown variant attribute"))
+            }
+        })?;
+    }
+    Ok(name)
+}
+
+fn fields(fields: &Fields) -> Result<(Style, Vec<Field<'_>>)> {
+    let style = match fields {
+        Fields::Named(_) => Style::Named,
+        Fields::Unnamed(_) => Style::Tuple,
+        Fields::Unit => Style::Unit,
+    };
+
+    let mut out = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(i.into()),
+        };
+        let mut out_field = Field {
+            name: field.ident.as_ref().map_or_else(|| i.to_string(), Ident::to_string),
+            member,
+            ty: &field.ty,
+            skip: false,
+            flatten: false,
+            default: None,
+        };
+
+        for attr in json_attrs(&field.attrs) {
+            if style == Style::Tuple {
+                return Err(Error::new_spanned(attr, "attributes are only supported on named fields"));
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    out_field.name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("default") {
+                    out_field.default = Some(match meta.value() {
+                        Ok(value) => Default::Path(value.parse::<LitStr>()?.parse()?),
+                        Err(_) => Default::Trait,
+                    });
+                } else if meta.path.is_ident("skip") {
+                    out_field.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    out_field.flatten = true;
+                } else {
+                    return Err(meta.error("unknown field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        out.push(out_field);
+    }
+    Ok((style, out))
+}
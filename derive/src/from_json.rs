@@ -0,0 +1,231 @@
+//! `#[derive(FromJson)]`.
+
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use syn::{DeriveInput, Result};
+
+use crate::ast::{Body, Container, Default, Field, Style, Tagging, Variant};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::from_input(input)?;
+    let ident = container.ident;
+    let generics = crate::with_bound(container.generics, quote!(::shift_reduce_json::unmarshal::FromJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &container.body {
+        Body::Struct(style, fields) => construct(&container, quote!(Self), *style, fields, &[]),
+        Body::Enum(variants) => from_enum(&container, variants),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::shift_reduce_json::unmarshal::FromJson for #ident #ty_generics #where_clause {
+            #[allow(unused_imports, clippy::redundant_closure_call)]
+            fn from_json(
+                value: &::shift_reduce_json::unmarshal::JsonValue,
+            ) -> ::core::result::Result<Self, ::shift_reduce_json::unmarshal::UnmarshalError> {
+                use ::core::result::Result::{self, Err, Ok};
+                use ::core::option::Option::{None, Some};
+                use ::shift_reduce_json::unmarshal::{FromJson, JsonValue, PathSegment, UnmarshalError};
+                #body
+            }
+        }
+    })
+}
+
+// An expression of type `Result<Self, UnmarshalError>` that builds `path`
+// from `value`. It may return early with `?`, so callers that need to catch
+// the error wrap it in a closure. `allowed` lists members that belong to the
+// enclosing enum rather than to the fields.
+fn construct(container: &Container, path: TokenStream, style: Style, fields: &[Field], allowed: &[&str]) -> TokenStream {
+    match style {
+        Style::Named => {
+            let known: Vec<&str> = fields
+                .iter()
+                .filter(|f| !f.skip && !f.flatten)
+                .map(|f| f.name.as_str())
+                .chain(allowed.iter().copied())
+                .collect();
+            let deny = container.deny_unknown_fields.then(|| {
+                quote! {
+                    if let Some(key) = object.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+                        return Err(UnmarshalError::new(format!("unknown field `{}`", key)));
+                    }
+                }
+            });
+            // Flattened fields read the members no other field claims.
+            let rest = fields.iter().any(|f| f.flatten).then(|| {
+                quote! {
+                    let rest = JsonValue::Object(
+                        object
+                            .iter()
+                            .filter(|(key, _)| !FIELDS.contains(&key.as_str()))
+                            .map(|(key, value)| (key.clone(), value.clone()))
+                            .collect(),
+                    );
+                }
+            });
+            let known = (deny.is_some() || rest.is_some()).then(|| quote!(const FIELDS: &[&str] = &[#(#known),*];));
+            let inits = fields.iter().map(field_init);
+            quote! {{
+                let object = value.as_object().ok_or_else(|| UnmarshalError::invalid_type("an object", value))?;
+                #known
+                #deny
+                #rest
+                Ok(#path { #(#inits,)* })
+            }}
+        }
+        // Newtypes are transparent.
+        Style::Tuple if fields.len() == 1 => quote!(Ok(#path(FromJson::from_json(value)?))),
+        Style::Tuple => {
+            let len = fields.len();
+            let elements = (0..len).map(Literal::usize_unsuffixed).map(|i| {
+                quote!(FromJson::from_json(&elements[#i]).map_err(|err| err.at(PathSegment::Index(#i)))?)
+            });
+            quote! {{
+                let elements = value.as_array().ok_or_else(|| UnmarshalError::invalid_type("an array", value))?;
+                if elements.len() != #len {
+                    return Err(UnmarshalError::new(format!("expected {} elements, found {}", #len, elements.len())));
+                }
+                Ok(#path(#(#elements),*))
+            }}
+        }
+        Style::Unit => quote! {{
+            <()>::from_json(value)?;
+            Ok(#path)
+        }},
+    }
+}
+
+fn field_init(field: &Field) -> TokenStream {
+    let member = &field.member;
+    let name = &field.name;
+    let default = match &field.default {
+        Some(Default::Path(path)) => quote!(#path()),
+        _ => quote!(::core::default::Default::default()),
+    };
+
+    if field.skip {
+        quote!(#member: #default)
+    } else if field.flatten {
+        quote!(#member: FromJson::from_json(&rest)?)
+    } else if field.default.is_some() {
+        quote! {
+            #member: match object.get(#name) {
+                Some(value) => FromJson::from_json(value).map_err(|err| err.at(PathSegment::Key(#name.to_string())))?,
+                None => #default,
+            }
+        }
+    } else {
+        quote!(#member: object.field(#name)?)
+    }
+}
+
+// Catches the early returns of `construct` so the error can be placed
+// under `segment`.
+fn nested(construct: TokenStream, segment: TokenStream) -> TokenStream {
+    quote! {
+        (|| -> Result<Self, UnmarshalError> { #construct })().map_err(|err| err.at(#segment))
+    }
+}
+
+fn from_enum(container: &Container, variants: &[Variant]) -> TokenStream {
+    let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+    let expected = names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ");
+    let unknown = quote! {
+        other => Err(UnmarshalError::new(format!("unknown variant `{}`, expected one of {}", other, #expected)))
+    };
+    let path = |variant: &Variant| {
+        let ident = variant.ident;
+        quote!(Self::#ident)
+    };
+
+    match &container.tagging {
+        Tagging::External => {
+            let bare = variants.iter().map(|v| construct(container, path(v), v.style, &v.fields, &[]));
+            let wrapped = variants.iter().map(|v| {
+                nested(construct(container, path(v), v.style, &v.fields, &[]), quote!(PathSegment::Key(name.clone())))
+            });
+            quote! {
+                match value {
+                    JsonValue::String(name) => {
+                        let null = JsonValue::Null;
+                        let value = &null;
+                        match name.as_str() {
+                            #(#names => #bare,)*
+                            #unknown
+                        }
+                    }
+                    JsonValue::Object(object) if object.len() == 1 => {
+                        let (name, value) = object.iter().next().unwrap();
+                        match name.as_str() {
+                            #(#names => #wrapped,)*
+                            #unknown
+                        }
+                    }
+                    _ => Err(UnmarshalError::invalid_type("a string or an object with one key", value)),
+                }
+            }
+        }
+        Tagging::Internal { tag } => {
+            let arms = variants.iter().map(|v| construct(container, path(v), Style::Named, &v.fields, &[tag]));
+            let read_tag = read_tag(tag);
+            quote! {
+                #read_tag
+                match tag {
+                    #(#names => #arms,)*
+                    #unknown
+                }
+            }
+        }
+        Tagging::Adjacent { tag, content } => {
+            let arms = variants.iter().map(|v| {
+                nested(
+                    construct(container, path(v), v.style, &v.fields, &[]),
+                    quote!(PathSegment::Key(#content.to_string())),
+                )
+            });
+            let read_tag = read_tag(tag);
+            let deny = container.deny_unknown_fields.then(|| {
+                quote! {
+                    if let Some(key) = object.keys().find(|key| *key != #tag && *key != #content) {
+                        return Err(UnmarshalError::new(format!("unknown field `{}`", key)));
+                    }
+                }
+            });
+            quote! {
+                #read_tag
+                #deny
+                let null = JsonValue::Null;
+                let value = object.get(#content).unwrap_or(&null);
+                match tag {
+                    #(#names => #arms,)*
+                    #unknown
+                }
+            }
+        }
+        Tagging::Untagged => {
+            let attempts = variants.iter().map(|v| construct(container, path(v), v.style, &v.fields, &[]));
+            let message = format!("data did not match any variant of untagged enum {}", container.ident);
+            quote! {
+                #(
+                    if let Ok(variant) = (|| -> Result<Self, UnmarshalError> { #attempts })() {
+                        return Ok(variant);
+                    }
+                )*
+                Err(UnmarshalError::new(#message))
+            }
+        }
+    }
+}
+
+// Binds `object` and the variant name in `tag`.
+fn read_tag(tag: &str) -> TokenStream {
+    quote! {
+        let object = value.as_object().ok_or_else(|| UnmarshalError::invalid_type("an object", value))?;
+        let tag = match object.get(#tag) {
+            Some(JsonValue::String(tag)) => tag.as_str(),
+            Some(other) => return Err(UnmarshalError::invalid_type("a string", other).at(PathSegment::Key(#tag.to_string()))),
+            None => return Err(UnmarshalError::new(format!("missing field `{}`", #tag))),
+        };
+    }
+}
@@ -0,0 +1,41 @@
+//! `#[derive(FromJson, ToJson)]` for the conversion traits of
+//! `shift-reduce-json`, enabled there with the `derive` feature.
+//!
+//! Attributes are written `#[json(...)]`:
+//!
+//! - on a struct or enum: `deny_unknown_fields`, and for enums one of
+//!   `tag = "t"` (internally tagged), `tag = "t", content = "c"` (adjacently
+//!   tagged) or `untagged`; enums are externally tagged otherwise;
+//! - on a variant: `rename = "name"`;
+//! - on a named field: `rename = "name"`, `default`, `default = "path"`,
+//!   `skip` and `flatten`.
+
+use proc_macro2::TokenStream;
+use syn::{parse_macro_input, parse_quote, DeriveInput, Generics};
+
+mod ast;
+mod from_json;
+mod to_json;
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_json::expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_json::expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+// Requires `bound` of every type parameter.
+fn with_bound(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
@@ -0,0 +1,148 @@
+//! `#[derive(ToJson)]`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, Index, Member, Result};
+
+use crate::ast::{Body, Container, Field, Style, Tagging, Variant};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::from_input(input)?;
+    let ident = container.ident;
+    let generics = crate::with_bound(container.generics, quote!(::shift_reduce_json::marshal::ToJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &container.body {
+        Body::Struct(style, fields) => {
+            let accessors: Vec<TokenStream> = fields
+                .iter()
+                .map(|f| {
+                    let member = &f.member;
+                    quote!(&self.#member)
+                })
+                .collect();
+            payload(*style, fields, &accessors)
+        }
+        Body::Enum(variants) if variants.is_empty() => quote!(match *self {}),
+        Body::Enum(variants) => {
+            let arms = variants.iter().map(|v| variant_arm(&container.tagging, v));
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::shift_reduce_json::marshal::ToJson for #ident #ty_generics #where_clause {
+            #[allow(unused_imports)]
+            fn to_json(&self) -> ::shift_reduce_json::unmarshal::JsonValue {
+                use ::shift_reduce_json::marshal::ToJson;
+                use ::shift_reduce_json::unmarshal::{JsonValue, Map};
+                #body
+            }
+        }
+    })
+}
+
+// Statements adding the members for `fields` to `map`; `accessors` give a
+// reference to each field.
+fn members(fields: &[Field], accessors: &[TokenStream]) -> TokenStream {
+    let inserts = fields.iter().zip(accessors).filter(|(f, _)| !f.skip).map(|(f, accessor)| {
+        let name = &f.name;
+        if f.flatten {
+            quote! {
+                if let JsonValue::Object(members) = ToJson::to_json(#accessor) {
+                    for (key, value) in members {
+                        map.insert(key, value);
+                    }
+                }
+            }
+        } else {
+            quote!(map.insert(#name.to_string(), ToJson::to_json(#accessor));)
+        }
+    });
+    quote!(#(#inserts)*)
+}
+
+fn payload(style: Style, fields: &[Field], accessors: &[TokenStream]) -> TokenStream {
+    match style {
+        Style::Named => {
+            let members = members(fields, accessors);
+            quote! {{
+                let mut map = Map::new();
+                #members
+                JsonValue::Object(map)
+            }}
+        }
+        Style::Tuple if fields.len() == 1 => {
+            let accessor = &accessors[0];
+            quote!(ToJson::to_json(#accessor))
+        }
+        Style::Tuple => quote!(JsonValue::Array(vec![#(ToJson::to_json(#accessors)),*])),
+        Style::Unit => quote!(JsonValue::Null),
+    }
+}
+
+fn variant_arm(tagging: &Tagging, variant: &Variant) -> TokenStream {
+    let ident = variant.ident;
+    let name = &variant.name;
+    let bindings: Vec<TokenStream> = (0..variant.fields.len())
+        .map(|i| {
+            let binding = format_ident!("__field{}", i);
+            quote!(#binding)
+        })
+        .collect();
+
+    let pattern = match variant.style {
+        Style::Named => {
+            let members = variant.fields.iter().zip(&bindings).filter(|(f, _)| !f.skip).map(|(f, binding)| {
+                let member = &f.member;
+                quote!(#member: #binding)
+            });
+            quote!(Self::#ident { #(#members,)* .. })
+        }
+        Style::Tuple => {
+            let members = variant.fields.iter().enumerate().map(|(i, _)| Member::Unnamed(Index::from(i)));
+            quote!(Self::#ident { #(#members: #bindings),* })
+        }
+        Style::Unit => quote!(Self::#ident),
+    };
+
+    let body = match tagging {
+        Tagging::External if variant.style == Style::Unit => quote!(JsonValue::String(#name.to_string())),
+        Tagging::External => {
+            let payload = payload(variant.style, &variant.fields, &bindings);
+            quote! {{
+                let mut map = Map::new();
+                map.insert(#name.to_string(), #payload);
+                JsonValue::Object(map)
+            }}
+        }
+        Tagging::Internal { tag } => {
+            let members = members(&variant.fields, &bindings);
+            quote! {{
+                let mut map = Map::new();
+                map.insert(#tag.to_string(), JsonValue::String(#name.to_string()));
+                #members
+                JsonValue::Object(map)
+            }}
+        }
+        Tagging::Adjacent { tag, content } => {
+            let content = (variant.style != Style::Unit).then(|| {
+                let payload = payload(variant.style, &variant.fields, &bindings);
+                quote!(map.insert(#content.to_string(), #payload);)
+            });
+            quote! {{
+                let mut map = Map::new();
+                map.insert(#tag.to_string(), JsonValue::String(#name.to_string()));
+                #content
+                JsonValue::Object(map)
+            }}
+        }
+        Tagging::Untagged => payload(variant.style, &variant.fields, &bindings),
+    };
+
+    quote!(#pattern => #body,)
+}
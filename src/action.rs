@@ -1,60 +1,81 @@
-use crate::grammar::{ElementType, JsonElement, StackElement, GRAMMAR};
+use crate::grammar::ElementType::{self, ArrayEnd, Comma, End, Exponent, Fraction, Integer, Number, ObjectEnd};
+use crate::table::{tables, Action};
 
-pub fn action(stack: Vec<StackElement>) -> (JsonElement, i32) {
-    let mut je: Option<JsonElement> = None;
-    let mut offset: i32 = 0;
-
-    for rule in &GRAMMAR {
-        for production in rule.rhs {
-            let size = production.len();
-
-            if size > stack.len() {
-                continue;
-            }
-
-            let actual = top_n_of_stack(&stack, size);
-            let matches = compare(production, &actual);
-
-            if matches && size as i32 > offset {
-                je = Some(JsonElement {
-                    value: Box::new((rule.to_json)(&stack[stack.len() - size..])),
-                    element_type: rule.lhs.clone(),
-                });
-                offset = size as i32;
-            }
-        }
+// Looks up what to do in `state` when `lookahead` is the next terminal.
+pub fn action(state: usize, lookahead: ElementType) -> Action {
+    let tables = tables();
+    match tables.terminal(lookahead) {
+        Some(t) => tables.action[state][t],
+        None => Action::Error,
     }
+}
 
-    (je.unwrap(), offset)
+// The state to enter after reducing to `lhs` on top of `state`.
+pub fn goto(state: usize, lhs: ElementType) -> Option<usize> {
+    let tables = tables();
+    tables.nonterminal(lhs).and_then(|n| tables.goto[state][n])
 }
 
-fn top_n_of_stack<'a>(stack: &[StackElement<'a>], count: usize) -> Vec<ElementType<'a>> {
-    let slice = &stack[stack.len() - count..];
+// Lists the terminals that can follow the parser's current stack of
+// states. LALR tables may hold a reduction on a terminal that gets rejected
+// once the reductions have been carried out, so each candidate is checked by
+// simulating them.
+pub fn expected_tokens(states: &[usize]) -> Vec<ElementType> {
+    let tables = tables();
+    let states = close_number(states);
+    tables
+        .terminals
+        .iter()
+        .filter(|terminal| accepts(&states, **terminal))
+        .copied()
+        .collect()
+}
 
-    let mut elements: Vec<ElementType<'a>>;
+// The lexer hands over numbers whole, so a number on top of the stack is
+// complete once the parser stops. Carries out the reductions that close it,
+// so that `.` and `e` are not offered after `1 `.
+fn close_number(states: &[usize]) -> Vec<usize> {
+    let tables = tables();
+    let mut states = states.to_vec();
 
-    for el in slice {
-        if let Some(token) = el.value {
-            elements.push(token.token_type.clone());
-        } else if let Some(rule) = el.rule {
-            elements.push(rule.element_type.clone());
+    loop {
+        let state = states[states.len() - 1];
+        let reduction = [End, Comma, ArrayEnd, ObjectEnd].into_iter().find_map(|follower| match action(state, follower) {
+            Action::Reduce(production) => Some(&tables.productions[production]),
+            _ => None,
+        });
+        match reduction {
+            Some(production) if matches!(production.lhs, Integer | Fraction | Exponent | Number) => {
+                states.truncate(states.len() - production.rhs.len());
+                match goto(states[states.len() - 1], production.lhs) {
+                    Some(next) => states.push(next),
+                    None => break,
+                }
+            }
+            _ => break,
         }
     }
-
-    elements
+    states
 }
 
+// Whether `terminal` can come next, once any reductions it calls for are
+// carried out.
+pub fn accepts(states: &[usize], terminal: ElementType) -> bool {
+    let tables = tables();
+    let mut states = states.to_vec();
 
-fn compare<T: PartialEq>(expansion: &[T], actual: &[T]) -> bool {
-    if expansion.len() != actual.len() {
-        return false;
-    }
-
-    for i in 0..expansion.len() {
-        if expansion[i] != actual[i] {
-            return false;
+    loop {
+        match action(states[states.len() - 1], terminal) {
+            Action::Shift(_) | Action::Accept => return true,
+            Action::Error => return false,
+            Action::Reduce(production) => {
+                let production = &tables.productions[production];
+                states.truncate(states.len() - production.rhs.len());
+                match goto(states[states.len() - 1], production.lhs) {
+                    Some(next) => states.push(next),
+                    None => return false,
+                }
+            }
         }
     }
-
-    true
 }
@@ -0,0 +1,208 @@
+//! A document tree that borrows its strings from the input where it can.
+//!
+//! Strings and keys without escapes are slices of the input; only those
+//! with escapes, which have to be decoded, are allocated.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::grammar::{ElementType, GrammarRule, StackElement};
+use crate::lexer::{Lexer, Token, TokenValue};
+use crate::number::Number;
+use crate::options::ParseOptions;
+use crate::parse::{ParseError, Parser};
+use crate::unmarshal::{JsonValue, Map};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum BorrowedValue<'a> {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    /// Members in document order. Duplicate keys are all kept;
+    /// [`get`](BorrowedValue::get) finds the last.
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+}
+
+/// Parses a complete JSON document, borrowing strings from `input`.
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>, ParseError> {
+    parse_borrowed_with(input, &ParseOptions::default())
+}
+
+pub fn parse_borrowed_with<'a>(input: &'a str, options: &ParseOptions) -> Result<BorrowedValue<'a>, ParseError> {
+    let mut lexer = Lexer::with_options(input.as_bytes(), options.clone()).borrowing_strings();
+    let mut parser = Parser::borrowing(options.clone(), input);
+    for token in &mut lexer {
+        parser.push(token?)?;
+    }
+    parser.finish(lexer.position())?;
+    Ok(parser.take_borrowed().unwrap_or_default())
+}
+
+impl<'a> BorrowedValue<'a> {
+    pub fn is_null(&self) -> bool {
+        matches!(self, BorrowedValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            BorrowedValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            BorrowedValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<BorrowedValue<'a>>> {
+        match self {
+            BorrowedValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Vec<(Cow<'a, str>, BorrowedValue<'a>)>> {
+        match self {
+            BorrowedValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        self.as_object()?.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Copies the borrowed strings to make a [`JsonValue`].
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            BorrowedValue::Null => JsonValue::Null,
+            BorrowedValue::Bool(b) => JsonValue::Bool(b),
+            BorrowedValue::Number(n) => JsonValue::Number(n),
+            BorrowedValue::String(s) => JsonValue::String(s.into_owned()),
+            BorrowedValue::Array(a) => JsonValue::Array(a.into_iter().map(BorrowedValue::into_owned).collect()),
+            BorrowedValue::Object(o) => {
+                JsonValue::Object(o.into_iter().map(|(k, v)| (k.into_owned(), v.into_owned())).collect::<Map>())
+            }
+        }
+    }
+}
+
+impl fmt::Display for BorrowedValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.clone().into_owned(), f)
+    }
+}
+
+impl From<JsonValue> for BorrowedValue<'_> {
+    fn from(value: JsonValue) -> Self {
+        match value {
+            JsonValue::Null => BorrowedValue::Null,
+            JsonValue::Bool(b) => BorrowedValue::Bool(b),
+            JsonValue::Number(n) => BorrowedValue::Number(n),
+            JsonValue::String(s) => BorrowedValue::String(Cow::Owned(s)),
+            JsonValue::Array(a) => BorrowedValue::Array(a.into_iter().map(BorrowedValue::from).collect()),
+            JsonValue::Object(o) => {
+                BorrowedValue::Object(o.into_iter().map(|(k, v)| (Cow::Owned(k), BorrowedValue::from(v))).collect())
+            }
+        }
+    }
+}
+
+enum Frame<'a> {
+    Array(Vec<BorrowedValue<'a>>),
+    // The members so far, and the key of the one being read.
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>, Option<Cow<'a, str>>),
+}
+
+// Builds a BorrowedValue as the parser shifts and reduces, the way the
+// event parser reports events: containers are opened when their bracket is
+// shifted and closed when they are reduced.
+pub(crate) struct Builder<'a> {
+    input: &'a str,
+    frames: Vec<Frame<'a>>,
+    root: Option<BorrowedValue<'a>>,
+}
+
+impl<'a> Builder<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Builder { input, frames: Vec::new(), root: None }
+    }
+
+    pub(crate) fn take(&mut self) -> Option<BorrowedValue<'a>> {
+        self.root.take()
+    }
+
+    pub(crate) fn shift(&mut self, token: &Token, stack: &mut [StackElement]) {
+        match token.token_type {
+            ElementType::ObjectStart => self.frames.push(Frame::Object(Vec::new(), None)),
+            ElementType::ArrayStart => self.frames.push(Frame::Array(Vec::new())),
+            ElementType::Colon => {
+                let key = match stack.last_mut().and_then(|element| element.value.as_mut()) {
+                    Some(token) => self.string(token),
+                    None => return,
+                };
+                if let Some(Frame::Object(_, pending)) = self.frames.last_mut() {
+                    *pending = Some(key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Stands in for `rule.to_json`, leaving a placeholder on the stack for
+    // whatever it has taken into the tree.
+    pub(crate) fn reduce(&mut self, rule: &GrammarRule, values: &mut [StackElement], options: &ParseOptions) -> JsonValue {
+        let value = match rule.lhs {
+            ElementType::Object => match self.frames.pop() {
+                Some(Frame::Object(members, _)) => BorrowedValue::Object(members),
+                _ => BorrowedValue::Null,
+            },
+            ElementType::Array => match self.frames.pop() {
+                Some(Frame::Array(elements)) => BorrowedValue::Array(elements),
+                _ => BorrowedValue::Null,
+            },
+            ElementType::Members | ElementType::Member | ElementType::Elements | ElementType::Element => return JsonValue::Null,
+            ElementType::Value => match (&values[0].rule, values[0].value.as_mut()) {
+                (Some(element), _) if element.element_type == ElementType::Object || element.element_type == ElementType::Array => {
+                    return JsonValue::Null;
+                }
+                (_, Some(token)) if token.token_type == ElementType::StringLiteral => BorrowedValue::String(self.string(token)),
+                _ => match (rule.to_json)(values, options) {
+                    JsonValue::Bool(b) => BorrowedValue::Bool(b),
+                    JsonValue::Number(n) => BorrowedValue::Number(n),
+                    _ => BorrowedValue::Null,
+                },
+            },
+            _ => return (rule.to_json)(values, options),
+        };
+
+        match self.frames.last_mut() {
+            Some(Frame::Array(elements)) => elements.push(value),
+            Some(Frame::Object(members, key)) => members.push((key.take().unwrap_or_default(), value)),
+            None => self.root = Some(value),
+        }
+        JsonValue::Null
+    }
+
+    // Takes the contents of a string token.
+    fn string(&self, token: &mut Token) -> Cow<'a, str> {
+        match std::mem::replace(&mut token.value, TokenValue::Null) {
+            TokenValue::Borrowed(range) => Cow::Borrowed(&self.input[range]),
+            TokenValue::String(s) => Cow::Owned(s),
+            _ => Cow::Borrowed(""),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Diagnostics over [`GRAMMAR`](crate::grammar::GRAMMAR): FIRST and FOLLOW
+//! sets, the LALR(1) item sets, and any shift/reduce or reduce/reduce
+//! conflicts among them.
+//!
+//! The parser tables resolve conflicts silently (shift wins, then the
+//! earlier production), so an edit to the grammar that introduces one only
+//! shows up here.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::grammar::ElementType;
+use crate::table::{Automaton, Grammar, Item, START};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+}
+
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub state: usize,
+    pub lookahead: ElementType,
+    pub kind: ConflictKind,
+    /// The items competing for `lookahead`, written as `lhs -> a . b`.
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemSet {
+    pub state: usize,
+    /// Each item with its lookaheads, written as `lhs -> a . b [x, y]`.
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GrammarReport {
+    pub first: Vec<(ElementType, Vec<ElementType>)>,
+    pub follow: Vec<(ElementType, Vec<ElementType>)>,
+    pub states: Vec<ItemSet>,
+    pub conflicts: Vec<Conflict>,
+}
+
+pub fn check_grammar() -> GrammarReport {
+    let grammar = Grammar::new();
+    let automaton = Automaton::new(&grammar);
+    let names = |set: &BTreeSet<usize>| set.iter().map(|&t| grammar.terminals[t]).collect::<Vec<_>>();
+
+    let first = grammar
+        .nonterminals
+        .iter()
+        .zip(&grammar.first)
+        .map(|(n, set)| (*n, names(set)))
+        .collect();
+
+    let follow = follow_sets(&grammar)
+        .iter()
+        .enumerate()
+        .map(|(n, set)| (grammar.nonterminals[n], names(set)))
+        .collect();
+
+    let mut states = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for state in 0..automaton.kernels.len() {
+        let items = automaton.items(&grammar, state);
+
+        states.push(ItemSet {
+            state,
+            items: items
+                .iter()
+                .map(|(item, lookahead)| format!("{} [{}]", render_item(&grammar, *item), join(&names(lookahead))))
+                .collect(),
+        });
+
+        for (t, terminal) in grammar.terminals.iter().enumerate() {
+            let shifts: Vec<Item> = items
+                .keys()
+                .filter(|item| grammar.next_symbol(**item) == Some(*terminal))
+                .copied()
+                .collect();
+            let reduces: Vec<Item> = items
+                .iter()
+                .filter(|(item, lookahead)| grammar.next_symbol(**item).is_none() && lookahead.contains(&t))
+                .map(|(item, _)| *item)
+                .collect();
+
+            let kind = match (shifts.is_empty(), reduces.len()) {
+                (false, 1..) => ConflictKind::ShiftReduce,
+                (true, 2..) => ConflictKind::ReduceReduce,
+                _ => continue,
+            };
+            conflicts.push(Conflict {
+                state,
+                lookahead: *terminal,
+                kind,
+                items: shifts.iter().chain(&reduces).map(|item| render_item(&grammar, *item)).collect(),
+            });
+        }
+    }
+
+    GrammarReport { first, follow, states, conflicts }
+}
+
+fn follow_sets(grammar: &Grammar) -> Vec<BTreeSet<usize>> {
+    let mut follow = vec![BTreeSet::new(); grammar.nonterminals.len()];
+    follow[grammar.nonterminal(START).unwrap()].insert(grammar.terminal(ElementType::End).unwrap());
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for production in &grammar.productions {
+            let lhs = grammar.nonterminal(production.lhs).unwrap();
+            for (i, symbol) in production.rhs.iter().enumerate() {
+                let n = match grammar.nonterminal(*symbol) {
+                    Some(n) => n,
+                    None => continue,
+                };
+
+                let (mut set, nullable) = grammar.first_of(&production.rhs[i + 1..]);
+                if nullable {
+                    set.extend(follow[lhs].clone());
+                }
+                let before = follow[n].len();
+                follow[n].extend(set);
+                changed |= follow[n].len() != before;
+            }
+        }
+    }
+
+    follow
+}
+
+fn render_item(grammar: &Grammar, (production, dot): Item) -> String {
+    let production = &grammar.productions[production];
+    let mut out = format!("{} ->", production.lhs);
+    for (i, symbol) in production.rhs.iter().enumerate() {
+        if i == dot {
+            out.push_str(" .");
+        }
+        out.push(' ');
+        out.push_str(symbol.name());
+    }
+    if dot == production.rhs.len() {
+        out.push_str(" .");
+    }
+    out
+}
+
+fn join(symbols: &[ElementType]) -> String {
+    symbols.iter().map(|symbol| symbol.name()).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictKind::ShiftReduce => write!(f, "shift/reduce"),
+            ConflictKind::ReduceReduce => write!(f, "reduce/reduce"),
+        }
+    }
+}
+
+impl fmt::Display for GrammarReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "FIRST")?;
+        for (n, set) in &self.first {
+            writeln!(f, "  {} = {{ {} }}", n, join(set))?;
+        }
+
+        writeln!(f, "\nFOLLOW")?;
+        for (n, set) in &self.follow {
+            writeln!(f, "  {} = {{ {} }}", n, join(set))?;
+        }
+
+        for set in &self.states {
+            writeln!(f, "\nstate {}", set.state)?;
+            for item in &set.items {
+                writeln!(f, "  {}", item)?;
+            }
+        }
+
+        writeln!(f)?;
+        if self.conflicts.is_empty() {
+            return writeln!(f, "no conflicts");
+        }
+        for conflict in &self.conflicts {
+            writeln!(f, "{} conflict in state {} on {}", conflict.kind, conflict.state, conflict.lookahead)?;
+            for item in &conflict.items {
+                writeln!(f, "  {}", item)?;
+            }
+        }
+        match self.conflicts.len() {
+            1 => writeln!(f, "1 conflict"),
+            n => writeln!(f, "{} conflicts", n),
+        }
+    }
+}
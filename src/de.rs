@@ -0,0 +1,377 @@
+//! Decoding Rust types that implement [`serde::Deserialize`] (available with
+//! the `serde` feature).
+//!
+//! The input is parsed into a [`BorrowedValue`] first, so fields of type
+//! `&str` can borrow strings that have no escapes.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, Unexpected, VariantAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::borrowed::{parse_borrowed, BorrowedValue};
+use crate::number::{Number, RAW_NUMBER_TOKEN};
+use crate::parse::ParseError;
+use crate::unmarshal::{JsonValue, Map};
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(ParseError),
+    /// The document does not match the type, as reported by its
+    /// `Deserialize` implementation.
+    Message(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(err) => write!(f, "{}", err),
+            Error::Message(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(err) => Some(err),
+            Error::Message(_) => None,
+        }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// Parses `input` as a `T`.
+pub fn from_str<'a, T: de::Deserialize<'a>>(input: &'a str) -> Result<T, Error> {
+    T::deserialize(parse_borrowed(input)?)
+}
+
+/// Converts a parsed document into a `T`.
+pub fn from_value<T: DeserializeOwned>(value: JsonValue) -> Result<T, Error> {
+    T::deserialize(BorrowedValue::from(value))
+}
+
+fn unexpected<'a>(value: &'a BorrowedValue) -> Unexpected<'a> {
+    match value {
+        BorrowedValue::Null => Unexpected::Unit,
+        BorrowedValue::Bool(b) => Unexpected::Bool(*b),
+        BorrowedValue::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => Unexpected::Unsigned(n),
+            (_, Some(n)) => Unexpected::Signed(n),
+            _ => Unexpected::Float(n.as_f64()),
+        },
+        BorrowedValue::String(s) => Unexpected::Str(s),
+        BorrowedValue::Array(_) => Unexpected::Seq,
+        BorrowedValue::Object(_) => Unexpected::Map,
+    }
+}
+
+// A raw number is read as a map holding its literal under
+// `RAW_NUMBER_TOKEN`, which `JsonValue` turns back into the number; types
+// that ask for a number get it decoded by `visit_decoded_number` instead.
+fn visit_number<'de, V: Visitor<'de>>(n: Number, visitor: V) -> Result<V::Value, Error> {
+    match n.as_raw() {
+        Some(lexeme) => {
+            let mut map = MapDeserializer::<_, Error>::new(std::iter::once((RAW_NUMBER_TOKEN, lexeme.to_string())));
+            let value = visitor.visit_map(&mut map)?;
+            map.end()?;
+            Ok(value)
+        }
+        None => visit_decoded_number(n, visitor),
+    }
+}
+
+fn visit_decoded_number<'de, V: Visitor<'de>>(n: Number, visitor: V) -> Result<V::Value, Error> {
+    if let Some(n) = n.as_u64() {
+        visitor.visit_u64(n)
+    } else if let Some(n) = n.as_i64() {
+        visitor.visit_i64(n)
+    } else {
+        visitor.visit_f64(n.as_f64())
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self {
+                    BorrowedValue::Number(n) => visit_decoded_number(n, visitor),
+                    other => other.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for BorrowedValue<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            BorrowedValue::Null => visitor.visit_unit(),
+            BorrowedValue::Bool(b) => visitor.visit_bool(b),
+            BorrowedValue::Number(n) => visit_number(n, visitor),
+            BorrowedValue::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            BorrowedValue::String(Cow::Owned(s)) => visitor.visit_string(s),
+            BorrowedValue::Array(elements) => {
+                let mut seq = SeqDeserializer::new(elements.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            BorrowedValue::Object(members) => {
+                let mut map = MapDeserializer::new(members.into_iter().map(|(key, value)| (Key(key), value)));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            BorrowedValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants are written as their name, others as an object with the
+    // name as its only key.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            BorrowedValue::String(variant) => visitor.visit_enum(Enum { variant, value: BorrowedValue::Null }),
+            BorrowedValue::Object(mut members) if members.len() == 1 => {
+                let (variant, value) = members.remove(0);
+                visitor.visit_enum(Enum { variant, value })
+            }
+            other => Err(de::Error::invalid_type(unexpected(&other), &"a string or an object with one key")),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
+    forward_to_deserialize_any! {
+        bool char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for BorrowedValue<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+// An object key. Besides strings, it can be read as the integers and
+// booleans that `ser` writes as key text.
+struct Key<'de>(Cow<'de, str>);
+
+impl Key<'_> {
+    fn parse<T: std::str::FromStr>(&self, expected: &str) -> Result<T, Error> {
+        self.0.parse().map_err(|_| de::Error::invalid_value(Unexpected::Str(&self.0), &expected))
+    }
+}
+
+macro_rules! deserialize_key_as {
+    ($($method:ident => $visit:ident, $expected:literal;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Key<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+
+    deserialize_key_as! {
+        deserialize_bool => visit_bool, "a boolean";
+        deserialize_i8 => visit_i8, "an i8";
+        deserialize_i16 => visit_i16, "an i16";
+        deserialize_i32 => visit_i32, "an i32";
+        deserialize_i64 => visit_i64, "an i64";
+        deserialize_u8 => visit_u8, "a u8";
+        deserialize_u16 => visit_u16, "a u16";
+        deserialize_u32 => visit_u32, "a u32";
+        deserialize_u64 => visit_u64, "a u64";
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(Enum { variant: self.0, value: BorrowedValue::Null })
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct seq
+        tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Key<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct Enum<'de> {
+    variant: Cow<'de, str>,
+    value: BorrowedValue<'de>,
+}
+
+impl<'de> EnumAccess<'de> for Enum<'de> {
+    type Error = Error;
+    type Variant = BorrowedValue<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(self.variant))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for BorrowedValue<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self {
+            BorrowedValue::Null => Ok(()),
+            other => Err(de::Error::invalid_type(unexpected(&other), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for JsonValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(Number::from(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(Number::from(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<JsonValue, E> {
+        Ok(Number::from_f64(v).map_or(JsonValue::Null, JsonValue::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut elements = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(JsonValue::Array(elements))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut members = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == RAW_NUMBER_TOKEN && members.is_empty() {
+                let lexeme: String = map.next_value()?;
+                return Number::from_raw(&lexeme)
+                    .map(JsonValue::Number)
+                    .ok_or_else(|| de::Error::invalid_value(Unexpected::Str(&lexeme), &"a JSON number"));
+            }
+            members.insert(key, map.next_value()?);
+        }
+        Ok(JsonValue::Object(members))
+    }
+}
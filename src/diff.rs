@@ -0,0 +1,386 @@
+//! Structural diff of two [`JsonValue`]s as a JSON Patch, and a readable
+//! rendering of a patch.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+
+use crate::pointer::escape;
+use crate::unmarshal::{JsonValue, Map};
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub arrays: ArrayDiff,
+}
+
+/// How elements of two arrays are paired up.
+#[derive(Debug, Clone, Default)]
+pub enum ArrayDiff {
+    /// Keep the longest common subsequence of equal elements; an element
+    /// that changed in place is diffed recursively. Arrays more than 2048
+    /// removals and additions apart are replaced whole.
+    #[default]
+    Lcs,
+    /// Pair objects that have the same value for this member, moving them
+    /// to their new positions. Other elements are only kept if they are
+    /// equal.
+    Key(String),
+}
+
+/// The RFC 6902 patch that turns `a` into `b`.
+pub fn diff(a: &JsonValue, b: &JsonValue) -> JsonValue {
+    diff_with(a, b, &DiffOptions::default())
+}
+
+pub fn diff_with(a: &JsonValue, b: &JsonValue, options: &DiffOptions) -> JsonValue {
+    let mut differ = Differ { options, ops: Vec::new() };
+    differ.value(a, b, String::new());
+    JsonValue::Array(differ.ops)
+}
+
+struct Differ<'a> {
+    options: &'a DiffOptions,
+    ops: Vec<JsonValue>,
+}
+
+fn op(name: &str, path: &str, value: Option<&JsonValue>, from: Option<&str>) -> JsonValue {
+    let mut op = Map::new();
+    op.insert("op".to_string(), JsonValue::String(name.to_string()));
+    if let Some(from) = from {
+        op.insert("from".to_string(), JsonValue::String(from.to_string()));
+    }
+    op.insert("path".to_string(), JsonValue::String(path.to_string()));
+    if let Some(value) = value {
+        op.insert("value".to_string(), value.clone());
+    }
+    JsonValue::Object(op)
+}
+
+impl Differ<'_> {
+    fn add(&mut self, path: &str, value: &JsonValue) {
+        self.ops.push(op("add", path, Some(value), None));
+    }
+
+    fn remove(&mut self, path: &str) {
+        self.ops.push(op("remove", path, None, None));
+    }
+
+    fn value(&mut self, a: &JsonValue, b: &JsonValue, path: String) {
+        if a == b {
+            return;
+        }
+        match (a, b) {
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                for (key, value) in a.iter() {
+                    let path = format!("{}/{}", path, escape(key));
+                    match b.get(key) {
+                        Some(other) => self.value(value, other, path),
+                        None => self.remove(&path),
+                    }
+                }
+                for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(key)) {
+                    self.add(&format!("{}/{}", path, escape(key)), value);
+                }
+            }
+            (JsonValue::Array(a), JsonValue::Array(b)) => match &self.options.arrays {
+                ArrayDiff::Lcs => self.lcs(a, b, &path),
+                ArrayDiff::Key(key) => self.keyed(a, b, key, &path),
+            },
+            _ => self.ops.push(op("replace", &path, Some(b), None)),
+        }
+    }
+
+    fn lcs(&mut self, a: &[JsonValue], b: &[JsonValue], path: &str) {
+        let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+        let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+        let Some(edits) = edit_script(&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]) else {
+            self.ops.push(op("replace", path, Some(&JsonValue::Array(b.to_vec())), None));
+            return;
+        };
+
+        // `pos` is where a[i] is in the array as patched so far.
+        let (mut i, mut j, mut pos) = (prefix, prefix, prefix);
+        let mut edits = edits.into_iter().peekable();
+        while let Some(edit) = edits.next() {
+            if edit == Edit::Keep {
+                i += 1;
+                j += 1;
+                pos += 1;
+                continue;
+            }
+
+            // Between two kept elements, pair removed elements with added
+            // ones and treat each pair as the same element changed in place.
+            let mut run = vec![edit];
+            while let Some(edit) = edits.next_if(|edit| *edit != Edit::Keep) {
+                run.push(edit);
+            }
+            let removed = run.iter().filter(|edit| **edit == Edit::Remove).count();
+            let added = run.len() - removed;
+            for _ in 0..removed.min(added) {
+                self.value(&a[i], &b[j], format!("{}/{}", path, pos));
+                i += 1;
+                j += 1;
+                pos += 1;
+            }
+            for _ in added..removed {
+                self.remove(&format!("{}/{}", path, pos));
+                i += 1;
+            }
+            for _ in removed..added {
+                self.add(&format!("{}/{}", path, pos), &b[j]);
+                j += 1;
+                pos += 1;
+            }
+        }
+    }
+
+    fn keyed(&mut self, a: &[JsonValue], b: &[JsonValue], key: &str, path: &str) {
+        // Elements of `a` by identity, to pair with those of `b` in order.
+        let mut unpaired: HashMap<Identity, VecDeque<usize>> = HashMap::new();
+        for (i, value) in a.iter().enumerate() {
+            unpaired.entry(Identity::of(value, key)).or_default().push_back(i);
+        }
+        let pairs: Vec<Option<usize>> = b
+            .iter()
+            .map(|target| unpaired.get_mut(&Identity::of(target, key)).and_then(VecDeque::pop_front))
+            .collect();
+
+        // The paired elements of `a` not yet moved into place, which follow
+        // those that have been in the patched array, in their original order.
+        let mut pending = Pending::new(a.len());
+        for &i in pairs.iter().flatten() {
+            pending.insert(i);
+        }
+        for i in (0..a.len()).rev().filter(|&i| !pending.contains(i)) {
+            self.remove(&format!("{}/{}", path, i));
+        }
+
+        for (j, pair) in pairs.into_iter().enumerate() {
+            match pair {
+                Some(source) => {
+                    let at = j + pending.before(source);
+                    if at != j {
+                        let from = format!("{}/{}", path, at);
+                        self.ops.push(op("move", &format!("{}/{}", path, j), None, Some(&from)));
+                    }
+                    pending.remove(source);
+                    self.value(&a[source], &b[j], format!("{}/{}", path, j));
+                }
+                None => self.add(&format!("{}/{}", path, j), &b[j]),
+            }
+        }
+    }
+}
+
+// What pairs an element with one in the other array: the value of its key
+// member, or the whole element for those without one.
+#[derive(PartialEq, Eq, Hash)]
+enum Identity<'a> {
+    Key(HashedValue<'a>),
+    Value(HashedValue<'a>),
+}
+
+impl<'a> Identity<'a> {
+    fn of(value: &'a JsonValue, key: &str) -> Self {
+        match value.get(key) {
+            Some(key) => Identity::Key(HashedValue(key)),
+            None => Identity::Value(HashedValue(value)),
+        }
+    }
+}
+
+// A value compared with `==`, and hashed so that equal values hash alike:
+// numbers by the `f64` they round to, and objects, whose member order does
+// not matter, by their size alone.
+struct HashedValue<'a>(&'a JsonValue);
+
+impl PartialEq for HashedValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for HashedValue<'_> {}
+
+impl Hash for HashedValue<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self.0).hash(state);
+        match self.0 {
+            JsonValue::Null => {}
+            JsonValue::Bool(b) => b.hash(state),
+            JsonValue::Number(n) => {
+                let f = n.as_f64();
+                // -0 equals 0.
+                (f + 0.0).to_bits().hash(state);
+            }
+            JsonValue::String(s) => s.hash(state),
+            JsonValue::Array(elements) => {
+                elements.len().hash(state);
+                for element in elements {
+                    HashedValue(element).hash(state);
+                }
+            }
+            JsonValue::Object(members) => members.len().hash(state),
+        }
+    }
+}
+
+// A set of indexes that counts its members below an index in logarithmic
+// time (a Fenwick tree).
+struct Pending {
+    members: Vec<bool>,
+    tree: Vec<usize>,
+}
+
+impl Pending {
+    fn new(len: usize) -> Self {
+        Pending { members: vec![false; len], tree: vec![0; len + 1] }
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.members[i]
+    }
+
+    fn insert(&mut self, i: usize) {
+        if !self.members[i] {
+            self.members[i] = true;
+            self.update(i, |count| count + 1);
+        }
+    }
+
+    fn remove(&mut self, i: usize) {
+        if self.members[i] {
+            self.members[i] = false;
+            self.update(i, |count| count - 1);
+        }
+    }
+
+    fn update(&mut self, i: usize, f: impl Fn(usize) -> usize) {
+        let mut node = i + 1;
+        while node < self.tree.len() {
+            self.tree[node] = f(self.tree[node]);
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    // How many members are less than `i`.
+    fn before(&self, i: usize) -> usize {
+        let mut count = 0;
+        let mut node = i;
+        while node > 0 {
+            count += self.tree[node];
+            node &= node - 1;
+        }
+        count
+    }
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Describes `patch` as applied to `doc`, one line per operation: `+` for
+/// additions, `-` for removals, `~` for replacements, `>` for moves and
+/// copies. With `color`, lines are wrapped in ANSI color codes. Operations
+/// that do not apply are shown as they are written.
+pub fn render(doc: &JsonValue, patch: &JsonValue, color: bool) -> String {
+    let mut out = String::new();
+    let mut doc = doc.clone();
+    let empty = Vec::new();
+    for operation in patch.as_array().unwrap_or(&empty) {
+        let field = |name: &str| operation.get(name).and_then(JsonValue::as_str).unwrap_or_default();
+        let path = field("path");
+        let display = |path: &str| if path.is_empty() { "(root)".to_string() } else { path.to_string() };
+        let value = operation.get("value").map(JsonValue::to_string).unwrap_or_default();
+        let old = doc.pointer(path).map(JsonValue::to_string).unwrap_or_default();
+
+        let (code, line) = match field("op") {
+            "add" => (GREEN, format!("+ {}: {}", display(path), value)),
+            "remove" => (RED, format!("- {}: {}", display(path), old)),
+            "replace" => (YELLOW, format!("~ {}: {} -> {}", display(path), old, value)),
+            op @ ("move" | "copy") => (CYAN, format!("> {} {} -> {}", op, display(field("from")), display(path))),
+            op => (RESET, format!("? {} {}", op, display(path))),
+        };
+        if color {
+            let _ = writeln!(out, "{}{}{}", code, line, RESET);
+        } else {
+            let _ = writeln!(out, "{}", line);
+        }
+
+        let _ = crate::patch::apply_patch(&mut doc, &JsonValue::Array(vec![operation.clone()]));
+    }
+    out
+}
+
+// How many elements may be removed and added between two arrays before the
+// differ gives up on pairing them. Finding the edits takes time proportional
+// to their number times the length of the arrays, and memory to its square.
+const MAX_EDITS: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+// The shortest edit script turning `a` into `b`, by Myers' algorithm, or
+// `None` if it is longer than `MAX_EDITS`.
+fn edit_script(a: &[JsonValue], b: &[JsonValue]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = MAX_EDITS.min(a.len() + b.len()) as isize;
+    // v[k + offset] is the furthest x reached on diagonal k = x - y.
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // The diagonals -d..=d of `v` as they were before each round d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: {
+        for d in 0..=max {
+            trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+            for k in (-d..=d).step_by(2) {
+                let down = k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+                let mut x = if down { v[(offset + k + 1) as usize] } else { v[(offset + k - 1) as usize] + 1 };
+                let mut y = x - k;
+                while x < n && y < m && a[x as usize] == b[y as usize] {
+                    x += 1;
+                    y += 1;
+                }
+                v[(offset + k) as usize] = x;
+                if x >= n && y >= m {
+                    break 'search;
+                }
+            }
+        }
+        return None;
+    }
+
+    // Walk back from the end, one edit per round.
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let k = x - y;
+            let at = |k: isize| v[(k + d) as usize];
+            let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+            (at(prev_k), at(prev_k) - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x { Edit::Add } else { Edit::Remove });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    edits.reverse();
+    Some(edits)
+}
@@ -0,0 +1,133 @@
+//! Event-driven parsing: the document is reported as a sequence of
+//! [`Event`]s, in order, as the parser shifts and reduces, without building
+//! a [`JsonValue`] for its arrays and objects.
+
+use std::io::BufRead;
+
+use crate::grammar::{token_text, ElementType, GrammarRule, StackElement};
+use crate::lexer::{Lexer, Token, TokenValue};
+use crate::options::ParseOptions;
+use crate::parse::{ParseError, Parser};
+use crate::unmarshal::JsonValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    /// The key of the object member whose value comes next.
+    Key(String),
+    EndObject,
+    StartArray,
+    EndArray,
+    /// A string, number, boolean or null.
+    Scalar(JsonValue),
+    /// A key holding a lone surrogate, in WTF-8 (see
+    /// [`LoneSurrogates::Preserve`](crate::LoneSurrogates::Preserve)).
+    Wtf8Key(Vec<u8>),
+    /// A string value holding a lone surrogate, in WTF-8.
+    Wtf8String(Vec<u8>),
+}
+
+/// Calls `handler` with each event of the document in `input`. Events up to
+/// a syntax error are still reported before it is returned.
+pub fn parse_events<F: FnMut(Event)>(input: &str, handler: F) -> Result<(), ParseError> {
+    parse_events_with(input, &ParseOptions::default(), handler)
+}
+
+pub fn parse_events_with<F: FnMut(Event)>(input: &str, options: &ParseOptions, mut handler: F) -> Result<(), ParseError> {
+    for event in Events::with_options(input.as_bytes(), options.clone()) {
+        handler(event?);
+    }
+    Ok(())
+}
+
+/// The events of the document read from `reader`, produced as the input
+/// is lexed. After an error the iterator ends.
+pub struct Events<R> {
+    lexer: Lexer<R>,
+    parser: Parser<'static>,
+    error: Option<ParseError>,
+    done: bool,
+}
+
+impl<R: BufRead> Events<R> {
+    pub fn new(reader: R) -> Self {
+        Events::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Events {
+            lexer: Lexer::with_options(reader, options.clone()),
+            parser: Parser::with_events(options),
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Events<R> {
+    type Item = Result<Event, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                return Some(Ok(event));
+            }
+            if let Some(err) = self.error.take() {
+                return Some(Err(err));
+            }
+            if self.done {
+                return None;
+            }
+
+            let step = match self.lexer.next() {
+                Some(Ok(token)) => self.parser.push(token),
+                Some(Err(err)) => Err(err.into()),
+                None => {
+                    self.done = true;
+                    self.parser.finish(self.lexer.position()).map(|_| ())
+                }
+            };
+            if let Err(err) = step {
+                self.error = Some(err);
+                self.done = true;
+            }
+        }
+    }
+}
+
+// Opening brackets are reported when shifted; a key once the colon after it
+// is.
+pub(crate) fn shift(token: &Token, stack: &[StackElement], events: &mut impl Extend<Event>) {
+    let event = match token.token_type {
+        ElementType::ObjectStart => Event::StartObject,
+        ElementType::ArrayStart => Event::StartArray,
+        ElementType::Colon => match stack.last() {
+            Some(StackElement { value: Some(Token { value: TokenValue::Wtf8(bytes), .. }), .. }) => Event::Wtf8Key(bytes.clone()),
+            Some(key) => Event::Key(token_text(key)),
+            None => return,
+        },
+        _ => return,
+    };
+    events.extend([event]);
+}
+
+// Stands in for `rule.to_json`. Containers report their end instead of being
+// built, and scalars are reported once they reduce to <value>, so only a
+// placeholder is left on the stack for either.
+pub(crate) fn reduce(rule: &GrammarRule, values: &mut [StackElement], options: &ParseOptions, events: &mut impl Extend<Event>) -> JsonValue {
+    let event = match rule.lhs {
+        ElementType::Object => Event::EndObject,
+        ElementType::Array => Event::EndArray,
+        ElementType::Members | ElementType::Member | ElementType::Elements | ElementType::Element => return JsonValue::Null,
+        ElementType::Value => match &values[0].rule {
+            Some(element) if element.element_type == ElementType::Object || element.element_type == ElementType::Array => return JsonValue::Null,
+            _ => match values[0].value.as_mut() {
+                Some(Token { value: TokenValue::Wtf8(bytes), .. }) => Event::Wtf8String(std::mem::take(bytes)),
+                _ => Event::Scalar((rule.to_json)(values, options)),
+            },
+        },
+        _ => return (rule.to_json)(values, options),
+    };
+    events.extend([event]);
+    JsonValue::Null
+}
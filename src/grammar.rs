@@ -1,213 +1,356 @@
-use std::any::Any;
-use crate::{lexer::Token, unmarshal::{JsonValue, JsonValueType}};
-
-pub type ElementType<'a> = &'a str;
-
-pub const NUMBER: ElementType = "<number>";
-pub const INTEGER: ElementType = "<integer>";
-pub const VALUE: ElementType = "<value>";
-pub const ARRAY: ElementType = "<array>";
-pub const MEMBERS: ElementType = "<object fields>";
-pub const MEMBER: ElementType = "<object field>";
-pub const ELEMENTS: ElementType = "<array elements>";
-pub const ELEMENT: ElementType = "<array element>";
-pub const OBJECT: ElementType = "<object>";
-pub const BOOLEAN: ElementType = "<boolean>";
-pub const EXPONENT: ElementType = "<exponent>";
-pub const FRACTION: ElementType = "<fraction>";
-// literal tokens
-pub const LT_OBJECT_START: ElementType = "{";
-pub const LT_OBJECT_END: ElementType = "}";
-pub const LT_ARRAY_START: ElementType = "[";
-pub const LT_ARRAY_END: ElementType = "]";
-pub const LT_COMMA: ElementType = ",";
-pub const LT_COLON: ElementType = ":";
-pub const LT_FRACTION_SYMBOL: ElementType = ".";
-pub const LT_BOOLEAN: ElementType = "<bool_literal>";
-pub const LT_EXPONENT: ElementType = "e/E";
-pub const LT_DIGITS: ElementType = "[0-9] (digits)";
-pub const LT_NULL: ElementType = "<null>";
-pub const LT_SIGN: ElementType = "+/-";
-pub const LT_STRING: ElementType = "<string_literal>";
-
-#[derive(Debug)]
-pub struct GrammarRule<'a> {
-    pub lhs: ElementType<'a>,
-    pub rhs: &'a [&'a [&'a str]],
-    pub to_json: fn(&[StackElement<'a>]) -> JsonValue<'a>,
+//! The JSON grammar as shift-reduce productions, with the reduction that
+//! builds a [`JsonValue`] for each of them.
+
+use std::fmt;
+
+use crate::{lexer::{Token, TokenValue}, number::Number, options::ParseOptions, unmarshal::{JsonValue, Map}};
+
+/// The terminals and non-terminals of the grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ElementType {
+    // The left-hand side of the augmented production `<start> -> <value>`.
+    Start,
+    Number,
+    Integer,
+    Value,
+    Array,
+    Members,
+    Member,
+    Elements,
+    Element,
+    Object,
+    Boolean,
+    Exponent,
+    Fraction,
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Comma,
+    Colon,
+    FractionSymbol,
+    BoolLiteral,
+    ExponentSymbol,
+    Digits,
+    NullLiteral,
+    Sign,
+    Minus,
+    StringLiteral,
+    End,
 }
 
-#[derive(Debug)]
-pub struct JsonElement<'a> {
-    pub value: Box<dyn Any>,
-    pub element_type: ElementType<'a>,
-}
+impl ElementType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ElementType::Start => "<start>",
+            ElementType::Number => "<number>",
+            ElementType::Integer => "<integer>",
+            ElementType::Value => "<value>",
+            ElementType::Array => "<array>",
+            ElementType::Members => "<object fields>",
+            ElementType::Member => "<object field>",
+            ElementType::Elements => "<array elements>",
+            ElementType::Element => "<array element>",
+            ElementType::Object => "<object>",
+            ElementType::Boolean => "<boolean>",
+            ElementType::Exponent => "<exponent>",
+            ElementType::Fraction => "<fraction>",
+            ElementType::ObjectStart => "{",
+            ElementType::ObjectEnd => "}",
+            ElementType::ArrayStart => "[",
+            ElementType::ArrayEnd => "]",
+            ElementType::Comma => ",",
+            ElementType::Colon => ":",
+            ElementType::FractionSymbol => ".",
+            ElementType::BoolLiteral => "<bool_literal>",
+            ElementType::ExponentSymbol => "e/E",
+            ElementType::Digits => "[0-9] (digits)",
+            ElementType::NullLiteral => "<null>",
+            ElementType::Sign => "+/-",
+            ElementType::Minus => "-",
+            ElementType::StringLiteral => "<string_literal>",
+            ElementType::End => "<end of input>",
+        }
+    }
 
-impl<'a> AsRef<JsonElement<'a>> for JsonElement<'a> {
-    fn as_ref(&self) -> &JsonElement<'a> {
-        self
+    /// How the symbol is described to users in error messages.
+    pub fn describe(self) -> &'static str {
+        match self {
+            ElementType::Start | ElementType::Value | ElementType::Element => "value",
+            ElementType::Number | ElementType::Integer | ElementType::Digits | ElementType::Minus => "number",
+            ElementType::Array | ElementType::Elements => "array",
+            ElementType::Object | ElementType::Members => "object",
+            ElementType::Member => "member",
+            ElementType::Boolean | ElementType::BoolLiteral => "boolean",
+            ElementType::Exponent | ElementType::ExponentSymbol => "'e'",
+            ElementType::Fraction | ElementType::FractionSymbol => "'.'",
+            ElementType::ObjectStart => "'{'",
+            ElementType::ObjectEnd => "'}'",
+            ElementType::ArrayStart => "'['",
+            ElementType::ArrayEnd => "']'",
+            ElementType::Comma => "','",
+            ElementType::Colon => "':'",
+            ElementType::NullLiteral => "null",
+            ElementType::Sign => "'+' or '-'",
+            ElementType::StringLiteral => "string",
+            ElementType::End => "end of input",
+        }
     }
 }
 
-impl<'a> JsonElement<'a> {
-    pub fn as_any(&self) -> &dyn Any {
-        self
+impl fmt::Display for ElementType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
     }
 }
 
-
-
 #[derive(Debug)]
-pub struct StackElement<'a> {
-    pub value: Option<&'a Token<'a>>,
-    pub rule: Option<JsonElement<'a>>,
+pub struct GrammarRule<'a> {
+    pub lhs: ElementType,
+    pub rhs: &'a [&'a [ElementType]],
+    pub to_json: fn(&mut [StackElement], &ParseOptions) -> JsonValue,
 }
 
+#[derive(Debug)]
+pub struct JsonElement {
+    pub value: JsonValue,
+    pub element_type: ElementType,
+}
 
+impl AsRef<JsonElement> for JsonElement {
+    fn as_ref(&self) -> &JsonElement {
+        self
+    }
+}
 
-fn value_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+#[derive(Debug)]
+pub struct StackElement {
+    pub value: Option<Token>,
+    pub rule: Option<JsonElement>,
+}
 
+// Takes the reduced value out of a non-terminal stack element.
+fn take_value(element: &mut StackElement) -> JsonValue {
+    match element.rule.as_mut() {
+        Some(rule) => std::mem::take(&mut rule.value),
+        None => JsonValue::Null,
+    }
 }
 
-fn boolean_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+pub(crate) fn token_text(element: &StackElement) -> String {
+    let token = match &element.value {
+        Some(token) => token,
+        None => return String::new(),
+    };
+
+    match &token.value {
+        TokenValue::String(s) | TokenValue::Digits(s) => s.clone(),
+        TokenValue::Punct(ch) => (*ch as char).to_string(),
+        TokenValue::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
 
+// <integer>, <fraction> and <exponent> are reduced to the source text they
+// cover (as a JsonValue::String) so that <number> can assemble the literal.
+fn lexeme(element: &mut StackElement) -> String {
+    match element.rule {
+        Some(_) => match take_value(element) {
+            JsonValue::String(s) => s,
+            _ => String::new(),
+        },
+        None => token_text(element),
+    }
 }
 
-fn object_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+fn value_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    let element = &mut values[0];
+    if element.rule.is_some() {
+        return take_value(element);
+    }
 
+    match &element.value {
+        Some(token) if token.token_type == ElementType::StringLiteral => JsonValue::String(token_text(element)),
+        _ => JsonValue::Null,
+    }
 }
 
+fn boolean_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    JsonValue::Bool(matches!(values[0].value, Some(Token { value: TokenValue::Bool(true), .. })))
+}
 
-fn members_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
-
+fn object_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    if values.len() == 2 {
+        return JsonValue::Object(Map::new());
+    }
+    take_value(&mut values[1])
 }
 
-fn member_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+// Duplicate keys keep the position of the first occurrence and the value of
+// the last one.
+fn members_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    if values.len() == 1 {
+        return take_value(&mut values[0]);
+    }
 
+    let mut members = match take_value(&mut values[0]) {
+        JsonValue::Object(map) => map,
+        _ => Map::new(),
+    };
+    if let JsonValue::Object(member) = take_value(&mut values[2]) {
+        for (key, value) in member {
+            members.insert(key, value);
+        }
+    }
+    JsonValue::Object(members)
 }
 
-fn array_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+fn member_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    let key = token_text(&values[0]);
+    let value = take_value(&mut values[2]);
 
+    let mut member = Map::new();
+    member.insert(key, value);
+    JsonValue::Object(member)
 }
-fn elements_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
 
+fn array_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    if values.len() == 2 {
+        return JsonValue::Array(Vec::new());
+    }
+    take_value(&mut values[1])
 }
 
-fn element_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+fn elements_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    if values.len() == 1 {
+        return JsonValue::Array(vec![take_value(&mut values[0])]);
+    }
 
+    let mut elements = match take_value(&mut values[0]) {
+        JsonValue::Array(elements) => elements,
+        _ => Vec::new(),
+    };
+    elements.push(take_value(&mut values[2]));
+    JsonValue::Array(elements)
 }
 
-fn number_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
-
+fn element_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    take_value(&mut values[0])
 }
 
-fn integer_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
-
+fn number_rule(values: &mut [StackElement], options: &ParseOptions) -> JsonValue {
+    let literal: String = values.iter_mut().map(lexeme).collect();
+    JsonValue::Number(Number::from_lexeme(&literal, options.raw_numbers))
 }
 
-fn fraction_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
-
+fn integer_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    JsonValue::String(values.iter_mut().map(lexeme).collect())
 }
 
-fn exponent_rule<'a>(values: &[StackElement]) -> JsonValue<'a> {
+fn fraction_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    JsonValue::String(format!(".{}", lexeme(&mut values[1])))
+}
 
+fn exponent_rule(values: &mut [StackElement], _: &ParseOptions) -> JsonValue {
+    JsonValue::String(values.iter_mut().map(lexeme).collect())
 }
 
 pub static GRAMMAR: [GrammarRule; 12] = [
     GrammarRule {
-        lhs: VALUE,
+        lhs: ElementType::Value,
         rhs: &[
-            &[OBJECT],
-            &[ARRAY],
-            &[NUMBER],
-            &[BOOLEAN],
-            &[LT_STRING],
-            &[LT_NULL],
+            &[ElementType::Object],
+            &[ElementType::Array],
+            &[ElementType::Number],
+            &[ElementType::Boolean],
+            &[ElementType::StringLiteral],
+            &[ElementType::NullLiteral],
         ],
         to_json: value_rule,
     },
     GrammarRule {
-        lhs: BOOLEAN,
+        lhs: ElementType::Boolean,
         rhs: &[
-            &[LT_BOOLEAN],
+            &[ElementType::BoolLiteral],
         ],
         to_json: boolean_rule,
     },
     GrammarRule {
-        lhs: OBJECT,
+        lhs: ElementType::Object,
         rhs: &[
-            &[LT_OBJECT_START, LT_OBJECT_END],
-            &[LT_OBJECT_START, MEMBERS, LT_OBJECT_END],
+            &[ElementType::ObjectStart, ElementType::ObjectEnd],
+            &[ElementType::ObjectStart, ElementType::Members, ElementType::ObjectEnd],
         ],
         to_json: object_rule,
     },
     GrammarRule {
-        lhs: MEMBERS,
+        lhs: ElementType::Members,
         rhs: &[
-            &[MEMBER],
-            &[MEMBERS, LT_COMMA, MEMBER],
+            &[ElementType::Member],
+            &[ElementType::Members, ElementType::Comma, ElementType::Member],
         ],
         to_json: members_rule,
     },
     GrammarRule {
-        lhs: MEMBER,
+        lhs: ElementType::Member,
         rhs: &[
-            &[LT_STRING, LT_COLON, VALUE],
+            &[ElementType::StringLiteral, ElementType::Colon, ElementType::Value],
         ],
         to_json: member_rule,
     },
     GrammarRule {
-        lhs: ARRAY,
+        lhs: ElementType::Array,
         rhs: &[
-            &[LT_ARRAY_START, LT_ARRAY_END],
-            &[LT_ARRAY_START, ELEMENTS, LT_ARRAY_END],
+            &[ElementType::ArrayStart, ElementType::ArrayEnd],
+            &[ElementType::ArrayStart, ElementType::Elements, ElementType::ArrayEnd],
         ],
         to_json: array_rule,
     },
     GrammarRule {
-        lhs: ELEMENTS,
+        lhs: ElementType::Elements,
         rhs: &[
-            &[ELEMENT],
-            &[ELEMENTS, LT_COMMA, ELEMENT],
+            &[ElementType::Element],
+            &[ElementType::Elements, ElementType::Comma, ElementType::Element],
         ],
         to_json: elements_rule,
     },
     GrammarRule {
-        lhs: ELEMENT,
+        lhs: ElementType::Element,
         rhs: &[
-            &[VALUE],
+            &[ElementType::Value],
         ],
         to_json: element_rule,
     },
     GrammarRule {
-        lhs: NUMBER,
+        lhs: ElementType::Number,
         rhs: &[
-            &[INTEGER, FRACTION, EXPONENT],
-            &[INTEGER, FRACTION],
-            &[INTEGER, EXPONENT],
-            &[INTEGER],
+            &[ElementType::Integer, ElementType::Fraction, ElementType::Exponent],
+            &[ElementType::Integer, ElementType::Fraction],
+            &[ElementType::Integer, ElementType::Exponent],
+            &[ElementType::Integer],
         ],
         to_json: number_rule,
     },
     GrammarRule {
-        lhs: INTEGER,
+        lhs: ElementType::Integer,
         rhs: &[
-            &[LT_DIGITS],
-            &[LT_SIGN, LT_DIGITS],
+            &[ElementType::Digits],
+            &[ElementType::Minus, ElementType::Digits],
         ],
         to_json: integer_rule,
     },
     GrammarRule {
-        lhs: FRACTION,
+        lhs: ElementType::Fraction,
         rhs: &[
-            &[LT_FRACTION_SYMBOL, LT_DIGITS],
+            &[ElementType::FractionSymbol, ElementType::Digits],
         ],
         to_json: fraction_rule,
     },
     GrammarRule {
-        lhs: EXPONENT,
+        lhs: ElementType::Exponent,
         rhs: &[
-            &[LT_EXPONENT, INTEGER],
+            &[ElementType::ExponentSymbol, ElementType::Digits],
+            &[ElementType::ExponentSymbol, ElementType::Sign, ElementType::Digits],
         ],
         to_json: exponent_rule,
     },
@@ -0,0 +1,450 @@
+//! A matcher for I-Regexp (RFC 9485), the regular expression
+//! subset JSONPath's `match()` and `search()` use, with an extended mode
+//! for the ECMA-262 patterns of JSON Schema.
+
+use crate::unicode::{self, GeneralCategory};
+
+// A compiled pattern: the instructions of a Thompson NFA, ending in
+// `Inst::Match`.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+}
+
+enum Node {
+    Char(char),
+    // Any character except `\n` and `\r`.
+    Any,
+    Class { negated: bool, items: Vec<ClassItem> },
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32> },
+    // `^` and `$`, in extended mode.
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    // `\p{..}` and `\P{..}`: the general categories of the name.
+    Category { categories: &'static [GeneralCategory], negated: bool },
+    // `\d`, `\w` and `\s` and their negations, in extended mode.
+    Escape { class: ClassEscape, negated: bool },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClassEscape {
+    Digit,
+    Word,
+    Space,
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class { negated: bool, items: Vec<ClassItem> },
+    // Continues at both instructions, preferring neither.
+    Split(usize, usize),
+    Jump(usize),
+    Start,
+    End,
+    Match,
+}
+
+// The most instructions a pattern may compile to once its counted repeats
+// are expanded.
+const MAX_PROGRAM: usize = 100_000;
+
+impl Regex {
+    /// Returns `None` if `pattern` is not a valid I-Regexp, uses a `\p{..}`
+    /// category RFC 9485 does not define, or repeats so much that it
+    /// expands to more than 100000 instructions.
+    pub fn new(pattern: &str) -> Option<Regex> {
+        Self::compile(pattern, false)
+    }
+
+    /// Accepts the ECMA-262 syntax JSON Schema patterns commonly use on top
+    /// of I-Regexp: `^` and `$` anchors, `\d`, `\w` and `\s` and their
+    /// negations, non-capturing groups, lazy quantifiers and escaped
+    /// punctuation. Use [`is_found`](Regex::is_found), as patterns are not
+    /// implicitly anchored.
+    pub fn extended(pattern: &str) -> Option<Regex> {
+        Self::compile(pattern, true)
+    }
+
+    fn compile(pattern: &str, extended: bool) -> Option<Regex> {
+        let mut parser = Parser { chars: pattern.chars().collect(), pos: 0, extended };
+        let root = parser.alternation()?;
+        if parser.pos != parser.chars.len() {
+            return None;
+        }
+        let mut program = Vec::new();
+        emit(&root, &mut program)?;
+        program.push(Inst::Match);
+        Some(Regex { program })
+    }
+
+    /// Whether the whole of `text` matches.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.run(text, true)
+    }
+
+    /// Whether some substring of `text` matches.
+    pub fn is_found(&self, text: &str) -> bool {
+        self.run(text, false)
+    }
+
+    // Steps every thread in lockstep over `text`, one character at a time,
+    // so matching takes time proportional to the program size times the
+    // text length whatever the pattern. Unless `anchored`, a new thread
+    // starts at every position.
+    fn run(&self, text: &str, anchored: bool) -> bool {
+        let input: Vec<char> = text.chars().collect();
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut stack = Vec::new();
+
+        for pos in 0..=input.len() {
+            if pos == 0 || !anchored {
+                self.follow(&mut current, 0, pos, input.len(), &mut stack);
+            }
+            for &pc in &current.pcs {
+                let c = input.get(pos);
+                let consumed = match &self.program[pc] {
+                    Inst::Char(ch) => c == Some(ch),
+                    Inst::Any => matches!(c, Some(c) if *c != '\n' && *c != '\r'),
+                    Inst::Class { negated, items } => c.is_some_and(|c| items.iter().any(|item| item.contains(*c)) != *negated),
+                    Inst::Match if !anchored || pos == input.len() => return true,
+                    _ => false,
+                };
+                if consumed {
+                    self.follow(&mut next, pc + 1, pos + 1, input.len(), &mut stack);
+                }
+            }
+            if anchored && next.pcs.is_empty() {
+                return false;
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        false
+    }
+
+    // Adds a thread at `pc` to `threads`, along with every instruction
+    // reachable from it through jumps, splits and anchors that hold at
+    // `pos`.
+    fn follow(&self, threads: &mut Threads, pc: usize, pos: usize, len: usize, stack: &mut Vec<usize>) {
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if std::mem::replace(&mut threads.seen[pc], true) {
+                continue;
+            }
+            threads.pcs.push(pc);
+            match self.program[pc] {
+                Inst::Split(a, b) => stack.extend([b, a]),
+                Inst::Jump(to) => stack.push(to),
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                _ => {}
+            }
+        }
+    }
+}
+
+// The instructions threads are at for one position, each at most once.
+struct Threads {
+    pcs: Vec<usize>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads { pcs: Vec::new(), seen: vec![false; len] }
+    }
+
+    fn clear(&mut self) {
+        for &pc in &self.pcs {
+            self.seen[pc] = false;
+        }
+        self.pcs.clear();
+    }
+}
+
+// Appends the instructions for `node`. Counted repeats are written out
+// once per iteration.
+fn emit(node: &Node, program: &mut Vec<Inst>) -> Option<()> {
+    if program.len() > MAX_PROGRAM {
+        return None;
+    }
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class { negated, items } => program.push(Inst::Class { negated: *negated, items: items.clone() }),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Concat(nodes) => {
+            for node in nodes {
+                emit(node, program)?;
+            }
+        }
+        Node::Alt(nodes) => {
+            let mut jumps = Vec::new();
+            for (i, node) in nodes.iter().enumerate() {
+                if i + 1 == nodes.len() {
+                    emit(node, program)?;
+                    break;
+                }
+                let split = program.len();
+                program.push(Inst::Split(split + 1, 0));
+                emit(node, program)?;
+                jumps.push(program.len());
+                program.push(Inst::Jump(0));
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+            for jump in jumps {
+                program[jump] = Inst::Jump(program.len());
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            // Repeating something that matches only the empty string
+            // changes nothing, however many times.
+            let start = program.len();
+            emit(node, program)?;
+            if program.len() == start {
+                return Some(());
+            }
+            program.truncate(start);
+
+            for _ in 0..*min {
+                emit(node, program)?;
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    emit(node, program)?;
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        emit(node, program)?;
+                    }
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, program.len());
+                    }
+                }
+            }
+        }
+    }
+    Some(())
+}
+
+impl ClassItem {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(lo, hi) => (*lo..=*hi).contains(&c),
+            ClassItem::Category { categories, negated } => {
+                categories.contains(&unicode::general_category(c)) != *negated
+            }
+            ClassItem::Escape { class, negated } => class.contains(c) != *negated,
+        }
+    }
+}
+
+impl ClassEscape {
+    fn contains(self, c: char) -> bool {
+        match self {
+            ClassEscape::Digit => c.is_ascii_digit(),
+            ClassEscape::Word => c.is_ascii_alphanumeric() || c == '_',
+            ClassEscape::Space => c.is_whitespace() || c == '\u{FEFF}',
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    extended: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn alternation(&mut self) -> Option<Node> {
+        let mut branches = vec![self.concatenation()?];
+        while self.eat('|') {
+            branches.push(self.concatenation()?);
+        }
+        Some(if branches.len() == 1 { branches.pop()? } else { Node::Alt(branches) })
+    }
+
+    fn concatenation(&mut self) -> Option<Node> {
+        let mut nodes = Vec::new();
+        while !matches!(self.peek(), None | Some('|') | Some(')')) {
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Some(Node::Concat(nodes))
+    }
+
+    fn quantified(&mut self, node: Node) -> Option<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?;
+                let max = if self.eat(',') {
+                    if self.peek() == Some('}') { None } else { Some(self.number()?) }
+                } else {
+                    Some(min)
+                };
+                if !self.eat('}') || max.is_some_and(|max| max < min) {
+                    return None;
+                }
+                self.lazy();
+                return Some(Node::Repeat { node: Box::new(node), min, max });
+            }
+            _ => return Some(node),
+        };
+        self.pos += 1;
+        self.lazy();
+        Some(Node::Repeat { node: Box::new(node), min, max })
+    }
+
+    // Whether a match exists does not depend on laziness, so `?` after a
+    // quantifier is accepted and ignored.
+    fn lazy(&mut self) {
+        if self.extended {
+            self.eat('?');
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        match self.next()? {
+            '(' => {
+                if self.extended && self.peek() == Some('?') {
+                    self.pos += 1;
+                    if !self.eat(':') {
+                        return None;
+                    }
+                }
+                let node = self.alternation()?;
+                self.eat(')').then_some(node)
+            }
+            '.' => Some(Node::Any),
+            '^' if self.extended => Some(Node::Start),
+            '$' if self.extended => Some(Node::End),
+            '[' => self.class(),
+            '\\' => match self.escape()? {
+                ClassItem::Range(c, _) => Some(Node::Char(c)),
+                item => Some(Node::Class { negated: false, items: vec![item] }),
+            },
+            '*' | '+' | '?' | '{' | '}' | ']' | ')' => None,
+            c => Some(Node::Char(c)),
+        }
+    }
+
+    fn class(&mut self) -> Option<Node> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        // A leading `-` is literal.
+        if self.eat('-') {
+            items.push(ClassItem::Range('-', '-'));
+        }
+        while !self.eat(']') {
+            let item = match self.next()? {
+                '\\' => self.escape()?,
+                '[' => return None,
+                c => ClassItem::Range(c, c),
+            };
+            let item = match item {
+                ClassItem::Range(lo, _) if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') => {
+                    self.pos += 1;
+                    let hi = match self.next()? {
+                        '\\' => match self.escape()? {
+                            ClassItem::Range(c, _) => c,
+                            _ => return None,
+                        },
+                        c => c,
+                    };
+                    if hi < lo {
+                        return None;
+                    }
+                    ClassItem::Range(lo, hi)
+                }
+                item => item,
+            };
+            items.push(item);
+        }
+        Some(Node::Class { negated, items })
+    }
+
+    fn escape(&mut self) -> Option<ClassItem> {
+        let c = match self.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            c @ ('p' | 'P') => {
+                if !self.eat('{') {
+                    return None;
+                }
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                if !self.eat('}') {
+                    return None;
+                }
+                return Some(ClassItem::Category { categories: unicode::categories(&name)?, negated: c == 'P' });
+            }
+            c @ ('(' | ')' | '*' | '+' | '-' | '.' | '?' | '[' | '\\' | ']' | '^' | '{' | '|' | '}') => c,
+            c @ ('d' | 'w' | 's' | 'D' | 'W' | 'S') if self.extended => {
+                let class = match c.to_ascii_lowercase() {
+                    'd' => ClassEscape::Digit,
+                    'w' => ClassEscape::Word,
+                    _ => ClassEscape::Space,
+                };
+                return Some(ClassItem::Escape { class, negated: c.is_ascii_uppercase() });
+            }
+            'f' if self.extended => '\u{0C}',
+            'v' if self.extended => '\u{0B}',
+            '0' if self.extended => '\0',
+            c if self.extended && c.is_ascii_punctuation() => c,
+            _ => return None,
+        };
+        Some(ClassItem::Range(c, c))
+    }
+}
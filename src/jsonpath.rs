@@ -0,0 +1,899 @@
+//! JSONPath ([RFC 9535]) queries over [`JsonValue`].
+//!
+//! A query is parsed once into a [`JsonPath`] and can then be run against
+//! any number of documents. Each result carries the normalized path of the
+//! node it came from.
+//!
+//! [RFC 9535]: https://www.rfc-editor.org/rfc/rfc9535
+
+use std::borrow::Cow;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::iregexp::Regex;
+use crate::number::Number;
+use crate::pointer;
+use crate::unmarshal::{JsonValue, PathSegment};
+
+/// A parsed JSONPath query.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+/// A node selected by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'v> {
+    pub path: NormalizedPath,
+    pub value: &'v JsonValue,
+}
+
+/// The location of a node as a sequence of member names and array indexes,
+/// written like `$['a'][0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedPath(Vec<PathSegment>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPathError {
+    /// Byte offset into the query.
+    pub offset: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice { start: Option<i64>, end: Option<i64>, step: Option<i64> },
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Comparable, Op, Comparable),
+    /// True when the query selects at least one node.
+    Exists(Query),
+    /// A function returning a logical result.
+    Call(Call),
+}
+
+#[derive(Debug, Clone)]
+struct Query {
+    /// `$` rather than `@`.
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Comparable {
+    Literal(JsonValue),
+    /// A query that selects at most one node.
+    Query(Query),
+    /// A function returning a value.
+    Call(Call),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+struct Call {
+    function: Function,
+    args: Vec<Arg>,
+    /// For `match()` and `search()` with a string literal pattern, the
+    /// pattern compiled once, or `None` if it is invalid.
+    regex: Option<Option<Regex>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Value,
+    Logical,
+    Nodes,
+}
+
+#[derive(Debug, Clone)]
+enum Arg {
+    Value(Comparable),
+    Nodes(Query),
+}
+
+impl Function {
+    fn signature(self) -> (&'static [Type], Type) {
+        match self {
+            Function::Length => (&[Type::Value], Type::Value),
+            Function::Count => (&[Type::Nodes], Type::Value),
+            Function::Match | Function::Search => (&[Type::Value, Type::Value], Type::Logical),
+            Function::Value => (&[Type::Nodes], Type::Value),
+        }
+    }
+}
+
+impl Query {
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(segment.selectors.as_slice(), [Selector::Name(_)] | [Selector::Index(_)])
+        })
+    }
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath, JsonPathError> {
+        let mut parser = Parser { input: query, pos: 0 };
+        parser.expect("$")?;
+        let segments = parser.segments()?;
+        if parser.pos != query.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// The nodes of `root` the query selects, in document order.
+    pub fn query<'v>(&self, root: &'v JsonValue) -> Vec<Match<'v>> {
+        let start = Node { value: root, path: None };
+        select(&self.segments, root, vec![start])
+            .into_iter()
+            .map(|node| Match { path: node.normalized_path(), value: node.value })
+            .collect()
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(query)
+    }
+}
+
+impl JsonValue {
+    /// Runs the JSONPath `query` against this value.
+    pub fn query(&self, query: &str) -> Result<Vec<Match<'_>>, JsonPathError> {
+        Ok(JsonPath::parse(query)?.query(self))
+    }
+}
+
+impl NormalizedPath {
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    /// The same location as a JSON Pointer.
+    pub fn to_pointer(&self) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => format!("/{}", pointer::escape(key)),
+                PathSegment::Index(i) => format!("/{}", i),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for NormalizedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.0 {
+            match segment {
+                PathSegment::Index(i) => write!(f, "[{}]", i)?,
+                PathSegment::Key(key) => {
+                    f.write_str("['")?;
+                    for c in key.chars() {
+                        match c {
+                            '\'' => f.write_str("\\'")?,
+                            '\\' => f.write_str("\\\\")?,
+                            '\u{08}' => f.write_str("\\b")?,
+                            '\u{0C}' => f.write_str("\\f")?,
+                            '\n' => f.write_str("\\n")?,
+                            '\r' => f.write_str("\\r")?,
+                            '\t' => f.write_str("\\t")?,
+                            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                            c => write!(f, "{}", c)?,
+                        }
+                    }
+                    f.write_str("']")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSONPath at offset {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+// Paths are built as linked lists while querying, so that selecting a node
+// does not copy the path of its parent.
+#[derive(Clone)]
+struct Node<'v> {
+    value: &'v JsonValue,
+    path: Option<Rc<Link<'v>>>,
+}
+
+struct Link<'v> {
+    parent: Option<Rc<Link<'v>>>,
+    step: Step<'v>,
+}
+
+enum Step<'v> {
+    Key(&'v str),
+    Index(usize),
+}
+
+impl<'v> Node<'v> {
+    fn child(&self, step: Step<'v>, value: &'v JsonValue) -> Node<'v> {
+        Node { value, path: Some(Rc::new(Link { parent: self.path.clone(), step })) }
+    }
+
+    fn normalized_path(&self) -> NormalizedPath {
+        let mut segments = Vec::new();
+        let mut link = self.path.as_deref();
+        while let Some(Link { parent, step }) = link {
+            segments.push(match step {
+                Step::Key(key) => PathSegment::Key(key.to_string()),
+                Step::Index(i) => PathSegment::Index(*i),
+            });
+            link = parent.as_deref();
+        }
+        segments.reverse();
+        NormalizedPath(segments)
+    }
+
+    fn children(&self) -> Vec<Node<'v>> {
+        match self.value {
+            JsonValue::Array(a) => a.iter().enumerate().map(|(i, v)| self.child(Step::Index(i), v)).collect(),
+            JsonValue::Object(o) => o.iter().map(|(k, v)| self.child(Step::Key(k), v)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The node followed by all of its descendants, in document order.
+    fn descendants(&self, out: &mut Vec<Node<'v>>) {
+        out.push(self.clone());
+        for child in self.children() {
+            child.descendants(out);
+        }
+    }
+}
+
+fn select<'v>(segments: &[Segment], root: &'v JsonValue, mut nodes: Vec<Node<'v>>) -> Vec<Node<'v>> {
+    for segment in segments {
+        let inputs = if segment.descendant {
+            let mut all = Vec::new();
+            for node in &nodes {
+                node.descendants(&mut all);
+            }
+            all
+        } else {
+            nodes
+        };
+        nodes = Vec::new();
+        for node in &inputs {
+            for selector in &segment.selectors {
+                apply(selector, root, node, &mut nodes);
+            }
+        }
+    }
+    nodes
+}
+
+fn apply<'v>(selector: &Selector, root: &'v JsonValue, node: &Node<'v>, out: &mut Vec<Node<'v>>) {
+    match (selector, node.value) {
+        (Selector::Name(name), JsonValue::Object(o)) => {
+            if let Some((key, value)) = o.get_key_value(name) {
+                out.push(node.child(Step::Key(key), value));
+            }
+        }
+        (Selector::Wildcard, _) => out.extend(node.children()),
+        (Selector::Index(i), JsonValue::Array(a)) => {
+            let i = if *i < 0 { a.len() as i64 + i } else { *i };
+            if (0..a.len() as i64).contains(&i) {
+                out.push(node.child(Step::Index(i as usize), &a[i as usize]));
+            }
+        }
+        (Selector::Slice { start, end, step }, JsonValue::Array(a)) => {
+            for i in slice_indexes(a.len() as i64, *start, *end, step.unwrap_or(1)) {
+                out.push(node.child(Step::Index(i), &a[i]));
+            }
+        }
+        (Selector::Filter(expr), _) => {
+            out.extend(node.children().into_iter().filter(|child| test(expr, root, child.value)));
+        }
+        _ => {}
+    }
+}
+
+// RFC 9535, section 2.3.4.2.2.
+fn slice_indexes(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indexes = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indexes.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indexes.push(i as usize);
+            i += step;
+        }
+    }
+    indexes
+}
+
+fn run<'v>(query: &Query, root: &'v JsonValue, current: &'v JsonValue) -> Vec<Node<'v>> {
+    let start = if query.absolute { root } else { current };
+    select(&query.segments, root, vec![Node { value: start, path: None }])
+}
+
+fn test(expr: &Expr, root: &JsonValue, current: &JsonValue) -> bool {
+    match expr {
+        Expr::Or(exprs) => exprs.iter().any(|e| test(e, root, current)),
+        Expr::And(exprs) => exprs.iter().all(|e| test(e, root, current)),
+        Expr::Not(e) => !test(e, root, current),
+        Expr::Compare(left, op, right) => {
+            let left = evaluate(left, root, current);
+            let right = evaluate(right, root, current);
+            compare(left.as_deref(), *op, right.as_deref())
+        }
+        Expr::Exists(query) => !run(query, root, current).is_empty(),
+        Expr::Call(call) => call_logical(call, root, current),
+    }
+}
+
+// `None` is the special result Nothing: an empty node list or a function
+// with no result.
+fn evaluate<'v>(comparable: &'v Comparable, root: &'v JsonValue, current: &'v JsonValue) -> Option<Cow<'v, JsonValue>> {
+    match comparable {
+        Comparable::Literal(value) => Some(Cow::Borrowed(value)),
+        Comparable::Query(query) => run(query, root, current).first().map(|node| Cow::Borrowed(node.value)),
+        Comparable::Call(call) => call_value(call, root, current),
+    }
+}
+
+fn compare(left: Option<&JsonValue>, op: Op, right: Option<&JsonValue>) -> bool {
+    match op {
+        Op::Eq => left == right,
+        Op::Ne => left != right,
+        Op::Lt => less(left, right),
+        Op::Le => less(left, right) || left == right,
+        Op::Gt => less(right, left),
+        Op::Ge => less(right, left) || left == right,
+    }
+}
+
+// Only numbers and strings are ordered.
+fn less(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (Some(JsonValue::Number(a)), Some(JsonValue::Number(b))) => a < b,
+        (Some(JsonValue::String(a)), Some(JsonValue::String(b))) => a < b,
+        _ => false,
+    }
+}
+
+fn value_arg<'v>(arg: &'v Arg, root: &'v JsonValue, current: &'v JsonValue) -> Option<Cow<'v, JsonValue>> {
+    match arg {
+        Arg::Value(comparable) => evaluate(comparable, root, current),
+        Arg::Nodes(_) => unreachable!(),
+    }
+}
+
+fn nodes_arg<'v>(arg: &Arg, root: &'v JsonValue, current: &'v JsonValue) -> Vec<Node<'v>> {
+    match arg {
+        Arg::Nodes(query) => run(query, root, current),
+        Arg::Value(_) => unreachable!(),
+    }
+}
+
+fn call_value<'v>(call: &'v Call, root: &'v JsonValue, current: &'v JsonValue) -> Option<Cow<'v, JsonValue>> {
+    match call.function {
+        Function::Length => {
+            let len = match value_arg(&call.args[0], root, current)?.as_ref() {
+                JsonValue::String(s) => s.chars().count(),
+                JsonValue::Array(a) => a.len(),
+                JsonValue::Object(o) => o.len(),
+                _ => return None,
+            };
+            Some(Cow::Owned(JsonValue::Number(Number::from(len as u64))))
+        }
+        Function::Count => {
+            let count = nodes_arg(&call.args[0], root, current).len();
+            Some(Cow::Owned(JsonValue::Number(Number::from(count as u64))))
+        }
+        Function::Value => match nodes_arg(&call.args[0], root, current).as_slice() {
+            [node] => Some(Cow::Borrowed(node.value)),
+            _ => None,
+        },
+        Function::Match | Function::Search => unreachable!(),
+    }
+}
+
+fn call_logical(call: &Call, root: &JsonValue, current: &JsonValue) -> bool {
+    let text = value_arg(&call.args[0], root, current);
+    let Some(JsonValue::String(text)) = text.as_deref() else {
+        return false;
+    };
+    // An invalid pattern makes the function false rather than the query an
+    // error, since the pattern may come from the document.
+    let compiled;
+    let regex = match &call.regex {
+        Some(regex) => regex.as_ref(),
+        None => match value_arg(&call.args[1], root, current).as_deref() {
+            Some(JsonValue::String(pattern)) => {
+                compiled = Regex::new(pattern);
+                compiled.as_ref()
+            }
+            _ => None,
+        },
+    };
+    match (call.function, regex) {
+        (Function::Match, Some(regex)) => regex.is_match(text),
+        (Function::Search, Some(regex)) => regex.is_found(text),
+        _ => false,
+    }
+}
+
+struct Parser<'q> {
+    input: &'q str,
+    pos: usize,
+}
+
+type ParseResult<T> = Result<T, JsonPathError>;
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> JsonPathError {
+        JsonPathError { offset: self.pos, message: message.to_string() }
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let found = self.rest().starts_with(s);
+        if found {
+            self.pos += s.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, s: &str) -> ParseResult<()> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", s)))
+        }
+    }
+
+    fn blank(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn segments(&mut self) -> ParseResult<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            let before = self.pos;
+            self.blank();
+            match self.segment()? {
+                Some(segment) => segments.push(segment),
+                None => {
+                    self.pos = before;
+                    return Ok(segments);
+                }
+            }
+        }
+    }
+
+    fn segment(&mut self) -> ParseResult<Option<Segment>> {
+        let descendant = if self.eat("..") {
+            true
+        } else if self.eat(".") {
+            false
+        } else if self.peek() == Some('[') {
+            return Ok(Some(Segment { descendant: false, selectors: self.bracketed()? }));
+        } else {
+            return Ok(None);
+        };
+
+        let selectors = if descendant && self.peek() == Some('[') {
+            self.bracketed()?
+        } else if self.eat("*") {
+            vec![Selector::Wildcard]
+        } else {
+            vec![Selector::Name(self.member_name()?)]
+        };
+        Ok(Some(Segment { descendant, selectors }))
+    }
+
+    fn member_name(&mut self) -> ParseResult<String> {
+        let start = self.pos;
+        let is_first = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+        match self.peek() {
+            Some(c) if is_first(c) => {}
+            _ => return Err(self.error("expected a member name")),
+        }
+        while let Some(c) = self.peek().filter(|c| is_first(*c) || c.is_ascii_digit()) {
+            self.pos += c.len_utf8();
+        }
+        Ok(self.input[start..self.pos].to_string())
+    }
+
+    fn bracketed(&mut self) -> ParseResult<Vec<Selector>> {
+        self.expect("[")?;
+        let mut selectors = Vec::new();
+        loop {
+            self.blank();
+            selectors.push(self.selector()?);
+            self.blank();
+            if !self.eat(",") {
+                self.expect("]")?;
+                return Ok(selectors);
+            }
+        }
+    }
+
+    fn selector(&mut self) -> ParseResult<Selector> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.blank();
+                Ok(Selector::Filter(self.or()?))
+            }
+            _ => {
+                let start = self.optional_int()?;
+                let before = self.pos;
+                self.blank();
+                if !self.eat(":") {
+                    self.pos = before;
+                    return start.map(Selector::Index).ok_or_else(|| self.error("expected a selector"));
+                }
+                self.blank();
+                let end = self.optional_int()?;
+                self.blank();
+                let step = if self.eat(":") {
+                    self.blank();
+                    self.optional_int()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    // Integers are limited to the range exactly representable as an f64.
+    fn optional_int(&mut self) -> ParseResult<Option<i64>> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text = &self.input[start..self.pos];
+        let digits = &self.input[digits..self.pos];
+        if digits.is_empty() {
+            if text.is_empty() {
+                return Ok(None);
+            }
+            return Err(self.error("expected digits"));
+        }
+        if (digits.len() > 1 && digits.starts_with('0')) || text == "-0" {
+            self.pos = start;
+            return Err(self.error("integers cannot have leading zeros or be -0"));
+        }
+        match text.parse::<i64>() {
+            Ok(n) if n.abs() < (1 << 53) => Ok(Some(n)),
+            _ => {
+                self.pos = start;
+                Err(self.error("integer out of range"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> ParseResult<String> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a string"))?;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                c if c == quote => return Ok(out),
+                '\\' => out.push(self.escape(quote)?),
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self, quote: char) -> ParseResult<char> {
+        let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += c.len_utf8();
+        Ok(match c {
+            'b' => '\u{08}',
+            'f' => '\u{0C}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '/' | '\\' => c,
+            c if c == quote => c,
+            'u' => {
+                let high = self.hex4()?;
+                if (0xD800..0xDC00).contains(&high) {
+                    self.expect("\\u")?;
+                    let low = self.hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("expected a low surrogate"));
+                    }
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                    return char::from_u32(code).ok_or_else(|| self.error("invalid escape"));
+                }
+                char::from_u32(high).ok_or_else(|| self.error("lone surrogate"))?
+            }
+            _ => return Err(self.error("invalid escape")),
+        })
+    }
+
+    fn hex4(&mut self) -> ParseResult<u32> {
+        let hex = self.rest().get(..4).filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()));
+        let code = hex.map(|h| u32::from_str_radix(h, 16).unwrap()).ok_or_else(|| self.error("expected 4 hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn or(&mut self) -> ParseResult<Expr> {
+        let mut exprs = vec![self.and()?];
+        loop {
+            let before = self.pos;
+            self.blank();
+            if !self.eat("||") {
+                self.pos = before;
+                break;
+            }
+            self.blank();
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.pop().unwrap() } else { Expr::Or(exprs) })
+    }
+
+    fn and(&mut self) -> ParseResult<Expr> {
+        let mut exprs = vec![self.basic()?];
+        loop {
+            let before = self.pos;
+            self.blank();
+            if !self.eat("&&") {
+                self.pos = before;
+                break;
+            }
+            self.blank();
+            exprs.push(self.basic()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.pop().unwrap() } else { Expr::And(exprs) })
+    }
+
+    fn basic(&mut self) -> ParseResult<Expr> {
+        if self.eat("!") {
+            self.blank();
+            if self.peek() == Some('(') {
+                return Ok(Expr::Not(Box::new(self.paren()?)));
+            }
+            let start = self.pos;
+            return match self.operand()? {
+                Operand::Query(query) => Ok(Expr::Not(Box::new(Expr::Exists(query)))),
+                Operand::Call(call, Type::Logical) => Ok(Expr::Not(Box::new(Expr::Call(call)))),
+                _ => Err(JsonPathError { offset: start, message: "expected a query or a logical function".to_string() }),
+            };
+        }
+        if self.peek() == Some('(') {
+            return self.paren();
+        }
+
+        let start = self.pos;
+        let left = self.operand()?;
+        let before = self.pos;
+        self.blank();
+        match self.op() {
+            Some(op) => {
+                let left = self.comparable(left, start)?;
+                self.blank();
+                let start = self.pos;
+                let right = self.operand()?;
+                let right = self.comparable(right, start)?;
+                Ok(Expr::Compare(left, op, right))
+            }
+            None => {
+                self.pos = before;
+                match left {
+                    Operand::Query(query) => Ok(Expr::Exists(query)),
+                    Operand::Call(call, Type::Logical) => Ok(Expr::Call(call)),
+                    _ => Err(JsonPathError { offset: start, message: "expected a comparison".to_string() }),
+                }
+            }
+        }
+    }
+
+    fn paren(&mut self) -> ParseResult<Expr> {
+        self.expect("(")?;
+        self.blank();
+        let expr = self.or()?;
+        self.blank();
+        self.expect(")")?;
+        Ok(expr)
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        let ops = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)];
+        ops.into_iter().find(|(text, _)| self.eat(text)).map(|(_, op)| op)
+    }
+
+    fn comparable(&self, operand: Operand, start: usize) -> ParseResult<Comparable> {
+        let error = |message: &str| JsonPathError { offset: start, message: message.to_string() };
+        match operand {
+            Operand::Literal(value) => Ok(Comparable::Literal(value)),
+            Operand::Query(query) if query.is_singular() => Ok(Comparable::Query(query)),
+            Operand::Query(_) => Err(error("only singular queries can be compared")),
+            Operand::Call(call, Type::Value) => Ok(Comparable::Call(call)),
+            Operand::Call(..) => Err(error("function does not return a value")),
+        }
+    }
+
+    fn operand(&mut self) -> ParseResult<Operand> {
+        match self.peek() {
+            Some('@') => {
+                self.pos += 1;
+                Ok(Operand::Query(Query { absolute: false, segments: self.segments()? }))
+            }
+            Some('$') => {
+                self.pos += 1;
+                Ok(Operand::Query(Query { absolute: true, segments: self.segments()? }))
+            }
+            Some('\'' | '"') => Ok(Operand::Literal(JsonValue::String(self.string()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_lowercase() => {
+                for (word, value) in [("true", JsonValue::Bool(true)), ("false", JsonValue::Bool(false)), ("null", JsonValue::Null)] {
+                    let follows = self.rest().strip_prefix(word).and_then(|rest| rest.chars().next());
+                    if self.rest().starts_with(word) && !follows.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '(') {
+                        self.pos += word.len();
+                        return Ok(Operand::Literal(value));
+                    }
+                }
+                self.call()
+            }
+            _ => Err(self.error("expected a literal, query or function")),
+        }
+    }
+
+    fn number(&mut self) -> ParseResult<Operand> {
+        let start = self.pos;
+        let rest = self.rest();
+        let mut end = 0;
+        let bytes = rest.as_bytes();
+        let digits = |mut i: usize| {
+            while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            }
+            i
+        };
+        if bytes.first() == Some(&b'-') {
+            end += 1;
+        }
+        end = digits(end);
+        if bytes.get(end) == Some(&b'.') {
+            end = digits(end + 1);
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            end += 1;
+            if matches!(bytes.get(end), Some(b'+' | b'-')) {
+                end += 1;
+            }
+            end = digits(end);
+        }
+        // JSONPath allows `-0`, which the JSON parser also accepts.
+        match crate::parse(&rest[..end]) {
+            Ok(value @ JsonValue::Number(_)) => {
+                self.pos += end;
+                Ok(Operand::Literal(value))
+            }
+            _ => Err(JsonPathError { offset: start, message: "invalid number".to_string() }),
+        }
+    }
+
+    fn call(&mut self) -> ParseResult<Operand> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            self.pos += 1;
+        }
+        let function = match &self.input[start..self.pos] {
+            "length" => Function::Length,
+            "count" => Function::Count,
+            "match" => Function::Match,
+            "search" => Function::Search,
+            "value" => Function::Value,
+            _ => return Err(JsonPathError { offset: start, message: "unknown function".to_string() }),
+        };
+        let (params, result) = function.signature();
+        self.expect("(")?;
+        let mut args = Vec::new();
+        for (i, param) in params.iter().enumerate() {
+            self.blank();
+            if i > 0 {
+                self.expect(",")?;
+                self.blank();
+            }
+            let arg_start = self.pos;
+            let operand = self.operand()?;
+            args.push(match (param, operand) {
+                (Type::Nodes, Operand::Query(query)) => Arg::Nodes(query),
+                (Type::Value, operand) => Arg::Value(self.comparable(operand, arg_start)?),
+                _ => return Err(JsonPathError { offset: arg_start, message: "expected a query".to_string() }),
+            });
+        }
+        self.blank();
+        self.expect(")")?;
+        let regex = match (function, args.get(1)) {
+            (Function::Match | Function::Search, Some(Arg::Value(Comparable::Literal(JsonValue::String(pattern))))) => {
+                Some(Regex::new(pattern))
+            }
+            _ => None,
+        };
+        Ok(Operand::Call(Call { function, args, regex }, result))
+    }
+}
+
+// Anything that can stand on either side of a comparison, before it is
+// known whether it is compared or tested.
+enum Operand {
+    Literal(JsonValue),
+    Query(Query),
+    Call(Call, Type),
+}
@@ -1,200 +1,649 @@
-use crate::grammar::{LT_ARRAY_END, LT_ARRAY_START, LT_BOOLEAN, LT_COLON, LT_COMMA, LT_DIGITS, LT_EXPONENT, LT_FRACTION_SYMBOL, LT_NULL, LT_OBJECT_END, LT_OBJECT_START, LT_SIGN, LT_STRING};
+//! Tokenizer turning JSON text into the terminals of [`crate::grammar::GRAMMAR`].
 
 use crate::grammar::ElementType;
-use std::collections::HashMap;
+use crate::options::{LoneSurrogates, ParseOptions};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead};
+use std::ops::Range;
 
-use std::any::Any;
 use std::fmt;
+/// A position in the input. `line` and `column` are 1-based, the column
+/// counting characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn start() -> Self {
+        Span { offset: 0, line: 1, column: 1 }
+    }
+
+    /// Moves forward to `offset`, counting the lines and characters of
+    /// `bytes` that are skipped over.
+    pub fn advanced_to(mut self, bytes: &[u8], offset: usize) -> Self {
+        for &b in &bytes[self.offset..offset] {
+            self.step(b);
+        }
+        self
+    }
+
+    // Moves past one byte of input.
+    fn step(&mut self, b: u8) {
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if b & 0xC0 != 0x80 {
+            self.column += 1;
+        }
+        self.offset += 1;
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span::start()
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
 #[derive(Debug)]
 pub enum TokenError {
-    StringLexFailure(String),
-    UnrecognizedTokenError
+    StringLexFailure(String, Span),
+    NumberLexFailure(String, Span),
+    UnrecognizedTokenError(Span),
+    /// Reading the input failed at `Span`.
+    Io(io::Error, Span),
 }
 
-pub struct Token<'a> {
-    pub value: Box<dyn Any>,
-    pub token_type: ElementType<'a>,
+impl TokenError {
+    pub fn span(&self) -> Span {
+        match self {
+            TokenError::StringLexFailure(_, span) => *span,
+            TokenError::NumberLexFailure(_, span) => *span,
+            TokenError::UnrecognizedTokenError(span) => *span,
+            TokenError::Io(_, span) => *span,
+        }
+    }
 }
 
-impl<'a> fmt::Debug for Token<'a> {
+impl fmt::Display for TokenError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(value) = self.value.downcast_ref::<i32>() {
-            write!(f, "Token {{ value: {:?}, token_type: {:?} }}", value, self.token_type)
-        } else if let Some(value) = self.value.downcast_ref::<f64>() {
-            write!(f, "Token {{ value: {:?}, token_type: {:?} }}", value, self.token_type)
-        } else if let Some(value) = self.value.downcast_ref::<String>() {
-            write!(f, "Token {{ value: {:?}, token_type: {:?} }}", value, self.token_type)
-        } else {
-            // If downcasting fails, fallback to a generic debug output.
-            write!(f, "Token {{ value: nil, token_type: {:?} }}", self.token_type)
+        match self {
+            TokenError::StringLexFailure(msg, span) => write!(f, "invalid string at {}: {}", span, msg),
+            TokenError::NumberLexFailure(msg, span) => write!(f, "invalid number at {}: {}", span, msg),
+            TokenError::UnrecognizedTokenError(span) => write!(f, "unrecognized token at {}", span),
+            TokenError::Io(err, span) => write!(f, "failed to read input at {}: {}", span, err),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TokenError::Io(err, _) => Some(err),
+            _ => None,
         }
     }
 }
 
-fn is_whitespace(ch: u8) -> bool {
-    ch == b' ' || ch == b'\t' || ch == b'\n'
+/// What a token holds besides its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenValue {
+    /// A structural character, or a part of a number other than its
+    /// digits: one of `{ } [ ] , : - . e E +`.
+    Punct(u8),
+    String(String),
+    /// A string without escapes, as the byte range of its contents in the
+    /// input. Only produced when lexing for a
+    /// [`BorrowedValue`](crate::BorrowedValue).
+    Borrowed(Range<usize>),
+    /// A string holding a lone surrogate, in WTF-8 (see
+    /// [`LoneSurrogates::Preserve`]).
+    Wtf8(Vec<u8>),
+    /// A run of digits in a number.
+    Digits(String),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub value: TokenValue,
+    pub token_type: ElementType,
+    pub span: Span,
+}
+
+pub(crate) fn is_whitespace(ch: u8) -> bool {
+    ch == b' ' || ch == b'\t' || ch == b'\n' || ch == b'\r'
 }
 
 fn is_digit(ch: u8) -> bool {
-    ch >= b'0' && ch <= b'9'
+    ch.is_ascii_digit()
 }
 
-fn init_special_symbols<'a>() -> HashMap<u8, ElementType<'a>> {
+fn init_special_symbols() -> HashMap<u8, ElementType> {
     let mut special_symbols = HashMap::new();
-    special_symbols.insert(b'{', LT_OBJECT_START);
-    special_symbols.insert(b'}', LT_OBJECT_END);
-    special_symbols.insert(b'[', LT_ARRAY_START);
-    special_symbols.insert(b']', LT_ARRAY_END);
-    special_symbols.insert(b',', LT_COMMA);
-    special_symbols.insert(b':', LT_COLON);
-    special_symbols.insert(b'.', LT_FRACTION_SYMBOL);
+    special_symbols.insert(b'{', ElementType::ObjectStart);
+    special_symbols.insert(b'}', ElementType::ObjectEnd);
+    special_symbols.insert(b'[', ElementType::ArrayStart);
+    special_symbols.insert(b']', ElementType::ArrayEnd);
+    special_symbols.insert(b',', ElementType::Comma);
+    special_symbols.insert(b':', ElementType::Colon);
     special_symbols
 }
 
 
 pub fn lex(input: &str) -> Result<Vec<Token>, TokenError> {
-    let special_symbols = init_special_symbols();
-    let mut tokens = Vec::new();
-    let chars: Vec<u8> = input.bytes().collect();
+    lex_with(input, &ParseOptions::default())
+}
+
+pub fn lex_with(input: &str, options: &ParseOptions) -> Result<Vec<Token>, TokenError> {
+    Lexer::with_options(input.as_bytes(), options.clone()).collect()
+}
+
+// Waits out interrupted reads, which `BufRead::fill_buf` passes on.
+fn fill_buf<R: BufRead>(reader: &mut R, at: Span) -> Result<&[u8], TokenError> {
+    loop {
+        match reader.fill_buf() {
+            Ok(_) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(TokenError::Io(err, at)),
+        }
+    }
+    reader.fill_buf().map_err(|err| TokenError::Io(err, at))
+}
+
+/// Pulls tokens from a [`BufRead`] one at a time, so the input never has to
+/// be held in memory as a whole. Tokens may be split across any number of
+/// the reader's buffers.
+///
+/// The iterator ends after the first error.
+pub struct Lexer<R> {
+    reader: R,
+    options: ParseOptions,
+    special_symbols: HashMap<u8, ElementType>,
+    position: Span,
+    // A number is lexed in one go and handed out a token at a time.
+    pending: VecDeque<Token>,
+    failed: bool,
+    // Strings without escapes are lexed into the range of the input they
+    // cover, to be borrowed from it, rather than copied.
+    borrow_strings: bool,
+    // Holds the contents of strings as they are lexed, and keeps its
+    // allocation while they are being borrowed.
+    scratch: Vec<u8>,
+    // With `suspend_strings`, a string cut off by the end of the input is
+    // kept in `partial_string`, and lexing carries on with it from there.
+    suspend_strings: bool,
+    partial_string: Option<PartialString>,
+}
+
+/// A string lexed up to the end of the input seen so far: its opening quote
+/// is at `span`, and `contents` holds what it decodes to up to there. The
+/// input stops short of any escape sequence that it would cut in two.
+pub(crate) struct PartialString {
+    span: Span,
+    contents: Vec<u8>,
+    escaped: bool,
+    wtf8: bool,
+}
+
+impl<R: BufRead> Lexer<R> {
+    pub fn new(reader: R) -> Self {
+        Lexer::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Lexer {
+            reader,
+            options,
+            special_symbols: init_special_symbols(),
+            position: Span::start(),
+            pending: VecDeque::new(),
+            failed: false,
+            borrow_strings: false,
+            scratch: Vec::new(),
+            suspend_strings: false,
+            partial_string: None,
+        }
+    }
+
+    /// How far the lexer has read; once it is exhausted, the end of the
+    /// input.
+    pub fn position(&self) -> Span {
+        self.position
+    }
+
+    // Carries on lexing the input from `position`, where `reader` starts.
+    pub(crate) fn resume(reader: R, options: ParseOptions, position: Span) -> Self {
+        Lexer {
+            position,
+            ..Lexer::with_options(reader, options)
+        }
+    }
+
+    // Lexes strings without escapes into `TokenValue::Borrowed` instead of
+    // `TokenValue::String`. Their contents are not checked to be UTF-8, so
+    // the input must be a `str`.
+    pub(crate) fn borrowing_strings(mut self) -> Self {
+        self.borrow_strings = true;
+        self
+    }
+
+    // Keeps a string that runs into the end of the input, for
+    // `take_partial_string`, rather than dropping it with the error.
+    pub(crate) fn suspending_strings(mut self) -> Self {
+        self.suspend_strings = true;
+        self
+    }
+
+    // Starts by carrying on with `string`, which the input continues.
+    pub(crate) fn continuing(mut self, string: Option<PartialString>) -> Self {
+        self.partial_string = string;
+        self
+    }
+
+    pub(crate) fn has_partial_string(&self) -> bool {
+        self.partial_string.is_some()
+    }
+
+    pub(crate) fn take_partial_string(&mut self) -> Option<PartialString> {
+        self.partial_string.take()
+    }
+
+    pub(crate) fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    // Drops the rest of the current line, and any error, to carry on lexing
+    // from the next one.
+    pub(crate) fn skip_line(&mut self) -> Result<(), TokenError> {
+        self.pending.clear();
+        self.failed = false;
+        while let Some(b) = self.peek()? {
+            self.bump(b);
+            if b == b'\n' {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Whether every token lexed so far has been handed out, which is not the
+    // case in the middle of a number.
+    pub(crate) fn is_between_tokens(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, TokenError> {
+        Ok(fill_buf(&mut self.reader, self.position)?.first().copied())
+    }
+
+    // Consumes `b`, which `peek` has just returned.
+    fn bump(&mut self, b: u8) {
+        self.reader.consume(1);
+        self.position.step(b);
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, TokenError> {
+        if let Some(token) = self.pending.pop_front() {
+            return Ok(Some(token));
+        }
+        if let Some(string) = self.partial_string.take() {
+            return self.lex_string_rest(string).map(Some);
+        }
 
-    let mut i = 0;
-    while i < chars.len() {
-        let ch = chars[i];
+        let ch = loop {
+            match self.peek()? {
+                Some(ch) if is_whitespace(ch) => self.bump(ch),
+                Some(ch) => break ch,
+                None => return Ok(None),
+            }
+        };
+        let span = self.position;
 
         match ch {
-            _ if special_symbols.contains_key(&ch) => {
-                if let Some(&token_type) = special_symbols.get(&ch) {
-                    tokens.push(Token {
-                        value: Box::new("nil"),
-                        token_type,
-                    });
-                }
-                i += 1;
+            _ if self.special_symbols.contains_key(&ch) => {
+                let token_type = self.special_symbols[&ch];
+                self.bump(ch);
+                Ok(Some(Token {
+                    value: TokenValue::Punct(ch),
+                    token_type,
+                    span,
+                }))
             }
             b'"' => {
-                let (token, offset) = match lex_string(input, i) {
-                    Ok((token, offset)) => (token, offset),
-                    Err(err) => {
-                        panic!("Failed to lex string: {:?}", err);
-                    }
-                };
-                tokens.push(token);
-                i += offset;
-            }
-            b't' => {
-                if input[i..i + 4] == *"true" {
-                    tokens.push(Token {
-                        value: Box::new("true"),
-                        token_type: LT_BOOLEAN,
-                    });
-                    i += 4;
-                } else {
-                    return Err(TokenError::UnrecognizedTokenError);
-                }
-            }
-            b'f' => {
-                if input[i..i + 5] == *"false" {
-                    tokens.push(Token {
-                        value: Box::new("false"),
-                        token_type: LT_BOOLEAN,
-                    });
-                    i += 5;
-                } else {
-                    return Err(TokenError::UnrecognizedTokenError);
-                }
+                self.bump(ch);
+                self.lex_string(span).map(Some)
             }
-            b'n' => {
-                if input[i..i + 4] == *"null" {
-                    tokens.push(Token {
-                        value: Box::new("null"),
-                        token_type: LT_NULL,
-                    });
-                    i += 4;
-                } else {
-                    return Err(TokenError::UnrecognizedTokenError);
-                }
+            b't' => self.lex_keyword("true", TokenValue::Bool(true), ElementType::BoolLiteral).map(Some),
+            b'f' => self.lex_keyword("false", TokenValue::Bool(false), ElementType::BoolLiteral).map(Some),
+            b'n' => self.lex_keyword("null", TokenValue::Null, ElementType::NullLiteral).map(Some),
+            b'-' | b'0'..=b'9' => {
+                self.lex_number()?;
+                Ok(self.pending.pop_front())
             }
-            b'e' | b'E' =>{
-                tokens.push(Token { value: Box::new("exp"), token_type: LT_EXPONENT });
-                i+=1;
+            _ => Err(TokenError::UnrecognizedTokenError(span)),
+        }
+    }
+
+    fn lex_keyword(&mut self, word: &str, value: TokenValue, token_type: ElementType) -> Result<Token, TokenError> {
+        let span = self.position;
+        for &expected in word.as_bytes() {
+            if self.peek()? != Some(expected) {
+                return Err(TokenError::UnrecognizedTokenError(span));
             }
-            b'+' | b'-' =>{
-                tokens.push(Token { value: Box::new(ch), token_type: LT_SIGN });
-                i+=1;
+            self.bump(expected);
+        }
+
+        Ok(Token {
+            value,
+            token_type,
+            span,
+        })
+    }
+
+    fn lex_digits(&mut self) -> Result<Token, TokenError> {
+        let span = self.position;
+        let mut str = String::new();
+        while let Some(b) = self.peek()?.filter(|&b| is_digit(b)) {
+            str.push(b as char);
+            self.bump(b);
+        }
+
+        Ok(Token {
+            token_type: ElementType::Digits,
+            value: TokenValue::Digits(str),
+            span,
+        })
+    }
+
+    // Lexes `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?` into its
+    // component tokens, queueing them in `pending`.
+    fn lex_number(&mut self) -> Result<(), TokenError> {
+        let expect_digit = |lexer: &mut Self, msg: &str| match lexer.peek()? {
+            Some(b) if is_digit(b) => Ok(()),
+            _ => Err(TokenError::NumberLexFailure(msg.to_string(), lexer.position)),
+        };
+
+        if self.peek()? == Some(b'-') {
+            self.pending.push_back(Token { value: TokenValue::Punct(b'-'), token_type: ElementType::Minus, span: self.position });
+            self.bump(b'-');
+        }
+
+        expect_digit(self, "expected a digit")?;
+        let token = self.lex_digits()?;
+        if let TokenValue::Digits(digits) = &token.value {
+            if digits.len() > 1 && digits.starts_with('0') {
+                return Err(TokenError::NumberLexFailure("leading zeros are not allowed".to_string(), token.span));
             }
+        }
+        self.pending.push_back(token);
+
+        if self.peek()? == Some(b'.') {
+            self.pending.push_back(Token { value: TokenValue::Punct(b'.'), token_type: ElementType::FractionSymbol, span: self.position });
+            self.bump(b'.');
+            expect_digit(self, "expected a digit after the decimal point")?;
+            let token = self.lex_digits()?;
+            self.pending.push_back(token);
+        }
 
-            _ if is_digit(ch) => {
-                let (token, offset) = lex_digits(input, i);
-                tokens.push(token);
-                i += offset;
+        if let Some(e @ (b'e' | b'E')) = self.peek()? {
+            self.pending.push_back(Token { value: TokenValue::Punct(e), token_type: ElementType::ExponentSymbol, span: self.position });
+            self.bump(e);
+            if let Some(sign @ (b'+' | b'-')) = self.peek()? {
+                self.pending.push_back(Token { value: TokenValue::Punct(sign), token_type: ElementType::Sign, span: self.position });
+                self.bump(sign);
             }
+            expect_digit(self, "expected a digit in the exponent")?;
+            let token = self.lex_digits()?;
+            self.pending.push_back(token);
+        }
+
+        Ok(())
+    }
+
+    // Lexes the rest of a string whose opening quote, at `span`, has been
+    // consumed.
+    fn lex_string(&mut self, span: Span) -> Result<Token, TokenError> {
+        let mut contents = std::mem::take(&mut self.scratch);
+        contents.clear();
+        self.lex_string_rest(PartialString { span, contents, escaped: false, wtf8: false })
+    }
+
+    fn lex_string_rest(&mut self, string: PartialString) -> Result<Token, TokenError> {
+        let PartialString { span, contents: mut sb, mut escaped, mut wtf8 } = string;
 
-            _ if is_whitespace(ch) => {
-                i += 1; // Skip whitespace
+        loop {
+            // Copy the run up to the next quote, escape or control character.
+            let buf = fill_buf(&mut self.reader, self.position)?;
+            if buf.is_empty() {
+                return self.cut_off(PartialString { span, contents: sb, escaped, wtf8 });
             }
-            _ => {
-                return Err(TokenError::UnrecognizedTokenError);
+            let stop = buf.iter().position(|&b| b == b'"' || b == b'\\' || b < 0x20);
+            let run = stop.unwrap_or(buf.len());
+            sb.extend_from_slice(&buf[..run]);
+            for &b in &buf[..run] {
+                self.position.step(b);
             }
+            let stop = stop.map(|i| buf[i]);
+            self.reader.consume(run);
+
+            match stop {
+                None => {}
+                Some(b'"') => {
+                    self.bump(b'"');
+                    break;
+                }
+                Some(b'\\') => {
+                    if self.suspend_strings && !escape_is_complete(fill_buf(&mut self.reader, self.position)?) {
+                        return self.cut_off(PartialString { span, contents: sb, escaped, wtf8 });
+                    }
+                    let escape = self.position;
+                    self.bump(b'\\');
+                    escaped = true;
+                    self.lex_escape(span, escape, &mut sb, &mut wtf8)?;
+                }
+                Some(_) => {
+                    return Err(TokenError::StringLexFailure("unescaped control character".to_string(), self.position));
+                }
+            }
+        }
+
+        if self.borrow_strings && !escaped {
+            self.scratch = sb;
+            return Ok(Token {
+                token_type: ElementType::StringLiteral,
+                value: TokenValue::Borrowed(span.offset + 1..self.position.offset - 1),
+                span,
+            });
         }
-        
+
+        // Escapes only produce whole code points, so the buffer is valid
+        // UTF-8 unless the input was not or a lone surrogate was preserved.
+        let value = match String::from_utf8(sb) {
+            Ok(s) if !wtf8 => TokenValue::String(s),
+            Ok(s) => TokenValue::Wtf8(s.into_bytes()),
+            Err(_) if !wtf8 => return Err(TokenError::StringLexFailure("invalid UTF-8".to_string(), span)),
+            Err(err) => TokenValue::Wtf8(err.into_bytes()),
+        };
+
+        Ok(Token {
+            token_type: ElementType::StringLiteral,
+            value,
+            span,
+        })
     }
 
-    Ok(tokens)
-}
+    // Fails on a string that runs into the end of the input, keeping it to
+    // be carried on if strings are being suspended.
+    fn cut_off(&mut self, string: PartialString) -> Result<Token, TokenError> {
+        let err = TokenError::StringLexFailure("string is not properly closed".to_string(), string.span);
+        if self.suspend_strings {
+            self.partial_string = Some(string);
+        }
+        Err(err)
+    }
 
-fn lex_digits(input: &str, mut i: usize) -> (Token, usize) {
-    let mut str = String::new();
-    let bytes = input.as_bytes();
+    // Reads the next byte of the string starting at `span`.
+    fn string_byte(&mut self, span: Span) -> Result<u8, TokenError> {
+        match self.peek()? {
+            Some(b) => {
+                self.bump(b);
+                Ok(b)
+            }
+            None => Err(TokenError::StringLexFailure("string is not properly closed".to_string(), span)),
+        }
+    }
 
-    while i < bytes.len() && is_digit(bytes[i]) {
-        str.push(bytes[i] as char);
-        i += 1;
+    // Reads the four hex digits of the `\u` escape at `escape`.
+    fn read_hex4(&mut self, escape: Span) -> Result<u16, TokenError> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            match self.peek()? {
+                Some(b) if hex_digit(b).is_some() => {
+                    unit = unit << 4 | hex_digit(b).unwrap_or(0);
+                    self.bump(b);
+                }
+                _ => return Err(TokenError::StringLexFailure("invalid \\u escape".to_string(), escape)),
+            }
+        }
+        Ok(unit)
     }
 
-    (
-        Token {
-            token_type: LT_DIGITS,
-            value: Box::new(str.clone()),
-        },
-        str.len(),
-    )
-}
-
-fn lex_string(input: &str, mut i: usize) -> Result<(Token, usize), TokenError> {
-    i += 1; // Move past the opening quote
-    let mut sb = String::new();
-    let bytes = input.as_bytes();
-
-    while i < bytes.len() && bytes[i] != b'"' {
-        if bytes[i] == b'\\' && i + 1 < bytes.len() {
-            // Handle escape sequence
-            i += 1;
-            match bytes[i] {
-                b'"' | b'\\' => sb.push(bytes[i] as char),
-                b'n' => sb.push('\n'),
-                b't' => sb.push('\t'),
-                b'r' => sb.push('\r'),
-                _ => {
-                    return Err(TokenError::StringLexFailure(format!("Invalid escape sequence at position {}", i)));
+    // Decodes the escape sequence whose backslash, at `escape`, has been
+    // consumed. A high surrogate followed by another escape carries on to
+    // that one, to pair them.
+    fn lex_escape(&mut self, span: Span, mut escape: Span, sb: &mut Vec<u8>, wtf8: &mut bool) -> Result<(), TokenError> {
+        let mut high: Option<(u16, Span)> = None;
+
+        loop {
+            let ch = self.string_byte(span)?;
+            let unit = match ch {
+                b'u' => Some(self.read_hex4(escape)?),
+                _ => None,
+            };
+
+            if let Some((high, at)) = high.take() {
+                match unit {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        push_code_point(sb, 0x10000 + ((high as u32 - 0xD800) << 10) + (low as u32 - 0xDC00));
+                        return Ok(());
+                    }
+                    _ => self.lone_surrogate(high, at, sb, wtf8)?,
+                }
+            }
+
+            match unit {
+                None => {
+                    let decoded = match ch {
+                        b'"' => b'"',
+                        b'\\' => b'\\',
+                        b'/' => b'/',
+                        b'b' => 0x08,
+                        b'f' => 0x0C,
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        _ if ch.is_ascii_graphic() => {
+                            let msg = format!("invalid escape sequence \\{}", ch as char);
+                            return Err(TokenError::StringLexFailure(msg, escape));
+                        }
+                        _ => return Err(TokenError::StringLexFailure("invalid escape sequence".to_string(), escape)),
+                    };
+                    sb.push(decoded);
+                    return Ok(());
+                }
+                Some(unit @ 0xD800..=0xDBFF) if self.peek()? == Some(b'\\') => {
+                    high = Some((unit, escape));
+                    escape = self.position;
+                    self.bump(b'\\');
+                }
+                Some(unit @ 0xD800..=0xDFFF) => return self.lone_surrogate(unit, escape, sb, wtf8),
+                Some(unit) => {
+                    push_code_point(sb, unit as u32);
+                    return Ok(());
                 }
             }
-        } else {
-            sb.push(bytes[i] as char);
         }
-        i += 1;
     }
 
-    if i >= bytes.len() || bytes[i] != b'"' {
-        return Err(TokenError::StringLexFailure("String is not properly closed".to_string()));
+    fn lone_surrogate(&self, unit: u16, escape: Span, sb: &mut Vec<u8>, wtf8: &mut bool) -> Result<(), TokenError> {
+        match self.options.lone_surrogates {
+            LoneSurrogates::Error => {
+                return Err(TokenError::StringLexFailure("lone UTF-16 surrogate".to_string(), escape));
+            }
+            LoneSurrogates::Replace => push_code_point(sb, 0xFFFD),
+            LoneSurrogates::Preserve => {
+                push_code_point(sb, unit as u32);
+                *wtf8 = true;
+            }
+        }
+        Ok(())
     }
+}
+
+impl<R: BufRead> Iterator for Lexer<R> {
+    type Item = Result<Token, TokenError>;
 
-    i += 1; // Move past the closing quote
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let token = self.next_token().transpose();
+        self.failed = matches!(token, Some(Err(_)));
+        token
+    }
+}
 
-    Ok((
-        Token {
-            token_type: LT_STRING,
-            value: Box::new(sb),
+// Whether `rest`, which starts with a backslash, holds the whole escape
+// sequence, along with the escape that may pair with a high surrogate.
+fn escape_is_complete(rest: &[u8]) -> bool {
+    match rest.get(1) {
+        None => false,
+        Some(b'u') => match rest.get(2..6) {
+            None => false,
+            Some(hex) if hex[0] | 0x20 == b'd' && matches!(hex[1] | 0x20, b'8' | b'9' | b'a' | b'b') => {
+                match (rest.get(6), rest.get(7)) {
+                    (None, _) | (Some(b'\\'), None) => false,
+                    (Some(b'\\'), Some(b'u')) => rest.len() >= 12,
+                    _ => true,
+                }
+            }
+            Some(_) => true,
         },
-        i,
-    ))
+        Some(_) => true,
+    }
+}
+
+fn hex_digit(b: u8) -> Option<u16> {
+    (b as char).to_digit(16).map(|d| d as u16)
+}
+
+// Appends a code point (possibly a lone surrogate) using the generalized
+// UTF-8 encoding, which for surrogates is WTF-8.
+fn push_code_point(out: &mut Vec<u8>, cp: u32) {
+    match cp {
+        0..=0x7F => out.push(cp as u8),
+        0x80..=0x7FF => out.extend_from_slice(&[0xC0 | (cp >> 6) as u8, 0x80 | (cp & 0x3F) as u8]),
+        0x800..=0xFFFF => out.extend_from_slice(&[
+            0xE0 | (cp >> 12) as u8,
+            0x80 | ((cp >> 6) & 0x3F) as u8,
+            0x80 | (cp & 0x3F) as u8,
+        ]),
+        _ => out.extend_from_slice(&[
+            0xF0 | (cp >> 18) as u8,
+            0x80 | ((cp >> 12) & 0x3F) as u8,
+            0x80 | ((cp >> 6) & 0x3F) as u8,
+            0x80 | (cp & 0x3F) as u8,
+        ]),
+    }
 }
@@ -0,0 +1,51 @@
+//! A JSON parser built on a shift-reduce automaton driven by the productions
+//! in [`grammar::GRAMMAR`].
+//!
+//! Documents are read with [`parse()`], [`parse_bytes`] or [`parse_reader`].
+//! There is no separate `from_str`: [`JsonValue`] implements
+//! [`FromStr`](std::str::FromStr), so `"[1, 2]".parse::<JsonValue>()` is the
+//! spelling for code written against that convention.
+
+pub mod borrowed;
+pub mod check;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diff;
+pub mod event;
+pub mod grammar;
+pub mod jsonpath;
+pub mod lexer;
+pub mod marshal;
+pub mod number;
+pub mod options;
+pub mod parse;
+pub mod patch;
+pub mod pointer;
+pub mod push;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod stream;
+pub mod unmarshal;
+pub mod validator;
+
+mod action;
+mod iregexp;
+mod table;
+mod unicode;
+mod util;
+
+pub use borrowed::{parse_borrowed, BorrowedValue};
+pub use diff::diff;
+pub use event::{parse_events, Event};
+pub use jsonpath::JsonPath;
+pub use marshal::{to_string, to_string_pretty, MarshalOptions, ToJson};
+pub use number::Number;
+pub use options::{LoneSurrogates, ParseOptions};
+pub use parse::{parse, parse_buf_reader, parse_bytes, parse_reader, parse_with, ParseError as Error};
+pub use patch::{apply_patch, merge_patch, PatchError};
+pub use pointer::PointerError;
+pub use push::PushParser;
+pub use unmarshal::{FromJson, JsonValue, Map, UnmarshalError};
+pub use validator::Validator;
+#[cfg(feature = "derive")]
+pub use shift_reduce_json_derive::{FromJson, ToJson};
@@ -1,20 +1,106 @@
-use lexer::lex;
-
-mod grammar;
-mod lexer;
-mod unmarshal;
-mod parse;
-mod util;
-mod action;
-//use crate::lexer;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process;
+
+use shift_reduce_json::check::check_grammar;
+use shift_reduce_json::diff::{diff_with, render, ArrayDiff, DiffOptions};
+use shift_reduce_json::{parse, JsonValue};
+
+const USAGE: &str = "usage: shift-reduce-json [FILE]
+       shift-reduce-json --check-grammar
+       shift-reduce-json --diff [--key FIELD] [--patch] OLD NEW
+
+--diff pairs up array elements by their longest common subsequence, replacing
+arrays more than 2048 removals and additions apart whole. With --key, objects
+with the same FIELD are paired and moved instead.";
+
+fn read_input(path: Option<String>) -> io::Result<String> {
+    match path {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            io::stdin().lock().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+fn read_document(path: &str) -> Result<JsonValue, String> {
+    let input = fs::read_to_string(path).map_err(|e| format!("error: failed to read {}: {}", path, e))?;
+    parse(&input).map_err(|e| format!("{}: {}", path, e.render(&input)))
+}
+
+// Exits like diff(1): 0 if the documents are equal, 1 if they differ and 2
+// on errors.
+fn run_diff(args: &[String]) -> ! {
+    let mut options = DiffOptions::default();
+    let mut as_patch = false;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => match args.next() {
+                Some(key) => options.arrays = ArrayDiff::Key(key.clone()),
+                None => usage(),
+            },
+            "--patch" => as_patch = true,
+            _ => paths.push(arg.as_str()),
+        }
+    }
+    let [old, new] = paths[..] else { usage() };
+
+    let (old, new) = match (read_document(old), read_document(new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
+    let patch = diff_with(&old, &new, &options);
+    if as_patch {
+        println!("{:#}", patch);
+    } else {
+        print!("{}", render(&old, &patch, io::stdout().is_terminal()));
+    }
+    process::exit(if patch.as_array().is_some_and(Vec::is_empty) { 0 } else { 1 });
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
 fn main() {
-    let res = lex(r#"{"hello": 12345}"#);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("--check-grammar") => {
+            let report = check_grammar();
+            print!("{}", report);
+            process::exit(if report.conflicts.is_empty() { 0 } else { 1 });
+        }
+        Some("--diff") => run_diff(&args[1..]),
+        Some("--help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {}
+    }
 
-    match res {
-        Ok(token) =>{
-            print!("{:?}", token);
+    let input = match read_input(args.into_iter().next()) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("error: failed to read input: {}", e);
+            process::exit(1);
         }
+    };
 
-        Err(e) => panic!("{:?}", e)
+    match parse(&input) {
+        Ok(value) => println!("{}", value),
+        Err(e) => {
+            eprintln!("{}", e.render(&input));
+            process::exit(1);
+        }
     }
 }
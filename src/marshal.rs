@@ -0,0 +1,328 @@
+//! Writing a [`JsonValue`] back out as JSON text, and [`ToJson`] for building
+//! one from Rust types.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::io;
+
+use crate::number::Number;
+use crate::unmarshal::JsonValue;
+
+#[derive(Debug, Clone, Default)]
+pub struct MarshalOptions {
+    /// Text repeated once per nesting level. `None` writes everything on one
+    /// line with no insignificant whitespace.
+    pub indent: Option<String>,
+    /// Write object members ordered by key instead of insertion order.
+    pub sort_keys: bool,
+    pub trailing_newline: bool,
+    /// Escape every non-ASCII character as `\uXXXX`.
+    pub ascii_only: bool,
+}
+
+impl MarshalOptions {
+    /// Two-space indentation and a trailing newline.
+    pub fn pretty() -> Self {
+        MarshalOptions {
+            indent: Some("  ".to_string()),
+            trailing_newline: true,
+            ..MarshalOptions::default()
+        }
+    }
+}
+
+pub fn to_string(value: &JsonValue) -> String {
+    to_string_with(value, &MarshalOptions::default())
+}
+
+pub fn to_string_pretty(value: &JsonValue) -> String {
+    to_string_with(value, &MarshalOptions::pretty())
+}
+
+pub fn to_string_with(value: &JsonValue, options: &MarshalOptions) -> String {
+    let mut out = String::new();
+    // Writing to a String cannot fail.
+    let _ = write_fmt(&mut out, value, options);
+    out
+}
+
+pub fn write_fmt<W: Write>(out: &mut W, value: &JsonValue, options: &MarshalOptions) -> fmt::Result {
+    let mut writer = Writer { out, options };
+    writer.value(value, 0)?;
+    if options.trailing_newline {
+        writer.out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+pub fn write_io<W: io::Write>(out: W, value: &JsonValue, options: &MarshalOptions) -> io::Result<()> {
+    let mut adapter = IoAdapter { inner: out, error: None };
+    match write_fmt(&mut adapter, value, options) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatter error"))),
+    }
+}
+
+// Forwards fmt::Write to io::Write, remembering the io::Error that a
+// fmt::Error cannot carry.
+struct IoAdapter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+struct Writer<'a, W> {
+    out: &'a mut W,
+    options: &'a MarshalOptions,
+}
+
+impl<W: Write> Writer<'_, W> {
+    fn value(&mut self, value: &JsonValue, depth: usize) -> fmt::Result {
+        match value {
+            JsonValue::Null => self.out.write_str("null"),
+            JsonValue::Bool(b) => write!(self.out, "{}", b),
+            JsonValue::Number(n) => write!(self.out, "{}", n),
+            JsonValue::String(s) => self.string(s),
+            JsonValue::Array(a) => {
+                if a.is_empty() {
+                    return self.out.write_str("[]");
+                }
+                self.out.write_char('[')?;
+                for (i, v) in a.iter().enumerate() {
+                    if i > 0 {
+                        self.out.write_char(',')?;
+                    }
+                    self.newline(depth + 1)?;
+                    self.value(v, depth + 1)?;
+                }
+                self.newline(depth)?;
+                self.out.write_char(']')
+            }
+            JsonValue::Object(o) => {
+                if o.is_empty() {
+                    return self.out.write_str("{}");
+                }
+                let mut members: Vec<_> = o.iter().collect();
+                if self.options.sort_keys {
+                    members.sort_by(|a, b| a.0.cmp(b.0));
+                }
+
+                self.out.write_char('{')?;
+                for (i, (k, v)) in members.into_iter().enumerate() {
+                    if i > 0 {
+                        self.out.write_char(',')?;
+                    }
+                    self.newline(depth + 1)?;
+                    self.string(k)?;
+                    self.out.write_char(':')?;
+                    if self.options.indent.is_some() {
+                        self.out.write_char(' ')?;
+                    }
+                    self.value(v, depth + 1)?;
+                }
+                self.newline(depth)?;
+                self.out.write_char('}')
+            }
+        }
+    }
+
+    fn newline(&mut self, depth: usize) -> fmt::Result {
+        if let Some(indent) = &self.options.indent {
+            self.out.write_char('\n')?;
+            for _ in 0..depth {
+                self.out.write_str(indent)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) -> fmt::Result {
+        self.out.write_char('"')?;
+        let mut start = 0;
+        for (i, ch) in s.char_indices() {
+            let escape = match ch {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                '\u{08}' => "\\b",
+                '\u{0C}' => "\\f",
+                c if (c as u32) < 0x20 || (self.options.ascii_only && !c.is_ascii()) => "",
+                _ => continue,
+            };
+
+            self.out.write_str(&s[start..i])?;
+            start = i + ch.len_utf8();
+            if !escape.is_empty() {
+                self.out.write_str(escape)?;
+                continue;
+            }
+            let mut units = [0u16; 2];
+            for unit in ch.encode_utf16(&mut units) {
+                write!(self.out, "\\u{:04x}", unit)?;
+            }
+        }
+        self.out.write_str(&s[start..])?;
+        self.out.write_char('"')
+    }
+}
+
+/// Conversion from a Rust type into a document tree.
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl ToJson for Number {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(self.clone())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Bool(*self)
+    }
+}
+
+impl ToJson for () {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Null
+    }
+}
+
+macro_rules! to_json_int {
+    ($($t:ty => $via:ty,)*) => {
+        $(
+            impl ToJson for $t {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Number(Number::from(*self as $via))
+                }
+            }
+        )*
+    };
+}
+
+to_json_int! {
+    i8 => i64, i16 => i64, i32 => i64, i64 => i64, isize => i64,
+    u8 => u64, u16 => u64, u32 => u64, u64 => u64, usize => u64,
+}
+
+/// NaN and infinities become `null`.
+impl ToJson for f64 {
+    fn to_json(&self) -> JsonValue {
+        Number::from_f64(*self).map_or(JsonValue::Null, JsonValue::Number)
+    }
+}
+
+impl ToJson for f32 {
+    fn to_json(&self) -> JsonValue {
+        (*self as f64).to_json()
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_ref().map_or(JsonValue::Null, T::to_json)
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(T::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: ToJson, const N: usize> ToJson for [T; N] {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+macro_rules! to_json_tuple {
+    ($(($($t:ident $i:tt),+),)*) => {
+        $(
+            impl<$($t: ToJson),+> ToJson for ($($t,)+) {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Array(vec![$(self.$i.to_json()),+])
+                }
+            }
+        )*
+    };
+}
+
+to_json_tuple! {
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7),
+}
+
+impl<T: ToJson, S> ToJson for HashMap<String, T, S> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for BTreeMap<String, T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
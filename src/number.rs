@@ -0,0 +1,320 @@
+//! JSON numbers that keep integers exact.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::unmarshal::JsonValue;
+
+/// A JSON number. Integers that fit in an `i64` or `u64` are stored exactly,
+/// anything else as an `f64`. In raw mode (see
+/// [`ParseOptions::raw_numbers`](crate::ParseOptions::raw_numbers)) the
+/// literal is kept verbatim and converted only on access; so is any literal
+/// too large for an `f64`, which therefore writes back unchanged.
+#[derive(Debug, Clone)]
+pub struct Number {
+    n: N,
+}
+
+// The name under which `ser` and `de` pass a raw number's literal through
+// serde, which has no data type for one.
+#[cfg(feature = "serde")]
+pub(crate) const RAW_NUMBER_TOKEN: &str = "$shift_reduce_json::private::RawNumber";
+
+#[derive(Debug, Clone)]
+enum N {
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+    Raw(String),
+}
+
+// A number normalized for comparisons.
+enum Numeric {
+    Int(i128),
+    Float(f64),
+    // A raw literal that is not an integer, compared exactly.
+    Decimal(Decimal),
+}
+
+// The value `0.digits × 10^exponent`, with `digits` free of leading and
+// trailing zeros (and empty for zero).
+#[derive(PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exponent: i64,
+}
+
+impl Number {
+    /// Builds a number from a literal the lexer has already validated.
+    pub(crate) fn from_lexeme(lexeme: &str, raw: bool) -> Self {
+        if !raw {
+            match Self::parse_lexeme(lexeme) {
+                N::Float(f) if f.is_infinite() => {}
+                n => return Number { n },
+            }
+        }
+        Number { n: N::Raw(lexeme.to_string()) }
+    }
+
+    fn parse_lexeme(lexeme: &str) -> N {
+        let is_integer = !lexeme.contains(['.', 'e', 'E']);
+
+        if is_integer {
+            if let Some(digits) = lexeme.strip_prefix('-') {
+                // -0 has no integer representation.
+                if digits.bytes().any(|b| b != b'0') {
+                    if let Ok(n) = lexeme.parse::<i64>() {
+                        return N::NegInt(n);
+                    }
+                }
+            } else if let Ok(n) = lexeme.parse::<u64>() {
+                return N::PosInt(n);
+            }
+        }
+
+        N::Float(lexeme.parse().unwrap_or(f64::NAN))
+    }
+
+    /// Returns `None` for NaN and infinities, which JSON cannot represent.
+    pub fn from_f64(f: f64) -> Option<Number> {
+        if f.is_finite() {
+            Some(Number { n: N::Float(f) })
+        } else {
+            None
+        }
+    }
+
+    /// Keeps `lexeme` as the number's text; it must be a valid JSON number.
+    pub fn from_raw(lexeme: &str) -> Option<Number> {
+        match crate::parse(lexeme) {
+            Ok(JsonValue::Number(_)) if lexeme.trim() == lexeme => Some(Number { n: N::Raw(lexeme.to_string()) }),
+            _ => None,
+        }
+    }
+
+    // Raw numbers are decoded the same way the parser would have.
+    fn cooked(&self) -> N {
+        match &self.n {
+            N::Raw(lexeme) => Self::parse_lexeme(lexeme),
+            n => n.clone(),
+        }
+    }
+
+    pub fn is_u64(&self) -> bool {
+        matches!(self.cooked(), N::PosInt(_))
+    }
+
+    pub fn is_i64(&self) -> bool {
+        match self.cooked() {
+            N::PosInt(n) => n <= i64::MAX as u64,
+            N::NegInt(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_f64(&self) -> bool {
+        matches!(self.cooked(), N::Float(_))
+    }
+
+    pub fn is_raw(&self) -> bool {
+        matches!(self.n, N::Raw(_))
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.cooked() {
+            N::PosInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.cooked() {
+            N::PosInt(n) => i64::try_from(n).ok(),
+            N::NegInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Always succeeds, possibly losing precision.
+    pub fn as_f64(&self) -> f64 {
+        match self.cooked() {
+            N::PosInt(n) => n as f64,
+            N::NegInt(n) => n as f64,
+            N::Float(f) => f,
+            N::Raw(_) => unreachable!(),
+        }
+    }
+
+    /// The original literal, for numbers parsed in raw mode or too large for
+    /// an `f64`.
+    pub fn as_raw(&self) -> Option<&str> {
+        match &self.n {
+            N::Raw(lexeme) => Some(lexeme),
+            _ => None,
+        }
+    }
+
+    fn numeric(&self) -> Numeric {
+        match (self.cooked(), &self.n) {
+            (N::PosInt(n), _) => Numeric::Int(n as i128),
+            (N::NegInt(n), _) => Numeric::Int(n as i128),
+            (_, N::Raw(lexeme)) => Numeric::Decimal(Decimal::parse(lexeme)),
+            (N::Float(f), _) => Numeric::Float(f),
+            (N::Raw(_), _) => unreachable!(),
+        }
+    }
+}
+
+impl Decimal {
+    // `text` is a JSON number literal, or the Debug form of a finite f64.
+    fn parse(text: &str) -> Decimal {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            // Exponents too large for an i64 saturate.
+            Some((mantissa, exponent)) => {
+                let exponent = exponent.strip_prefix('+').unwrap_or(exponent);
+                let saturated = if exponent.starts_with('-') { i64::MIN / 2 } else { i64::MAX / 2 };
+                (mantissa, exponent.parse::<i64>().unwrap_or(saturated).clamp(i64::MIN / 2, i64::MAX / 2))
+            }
+            None => (text, 0),
+        };
+        let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let digits: Vec<u8> = int.bytes().chain(fraction.bytes()).map(|b| b - b'0').collect();
+        let leading = digits.iter().take_while(|&&d| d == 0).count();
+        let trailing = digits[leading..].iter().rev().take_while(|&&d| d == 0).count();
+        let digits = digits[leading..digits.len() - trailing].to_vec();
+        if digits.is_empty() {
+            return Decimal { negative: false, digits, exponent: 0 };
+        }
+        Decimal { negative, exponent: exponent + int.len() as i64 - leading as i64, digits }
+    }
+
+    fn from_int(i: i128) -> Decimal {
+        Decimal::parse(&i.to_string())
+    }
+
+    // Floats compare as the shortest decimal that reads back as them, which
+    // is also how they are written out.
+    fn from_f64(f: f64) -> Decimal {
+        Decimal::parse(&format!("{:?}", f))
+    }
+
+    fn signum(&self) -> i8 {
+        match (self.digits.is_empty(), self.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let magnitude = (self.exponent, &self.digits).cmp(&(other.exponent, &other.digits));
+        match self.signum().cmp(&other.signum()) {
+            Ordering::Equal if self.signum() == 0 => Ordering::Equal,
+            Ordering::Equal if self.negative => magnitude.reverse(),
+            Ordering::Equal => magnitude,
+            ord => ord,
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn compare_int_float(i: i128, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    // Every i64/u64 is within (-2^127, 2^127), so a float outside that range
+    // decides the comparison on its own.
+    if f >= 2f64.powi(127) {
+        return Some(Ordering::Less);
+    }
+    if f < -(2f64.powi(127)) {
+        return Some(Ordering::Greater);
+    }
+    let t = f.trunc();
+    match i.cmp(&(t as i128)) {
+        Ordering::Equal if f > t => Some(Ordering::Less),
+        Ordering::Equal if f < t => Some(Ordering::Greater),
+        ord => Some(ord),
+    }
+}
+
+impl PartialOrd for Number {
+    /// Numbers compare by mathematical value, whatever their representation.
+    /// Raw literals, including those too large for an `f64`, compare
+    /// exactly; a float compares as the shortest decimal that reads back as
+    /// it.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.numeric(), other.numeric()) {
+            (Numeric::Int(a), Numeric::Int(b)) => Some(a.cmp(&b)),
+            (Numeric::Float(a), Numeric::Float(b)) => a.partial_cmp(&b),
+            (Numeric::Int(a), Numeric::Float(b)) => compare_int_float(a, b),
+            (Numeric::Float(a), Numeric::Int(b)) => compare_int_float(b, a).map(Ordering::reverse),
+            (Numeric::Decimal(a), Numeric::Decimal(b)) => Some(a.cmp(&b)),
+            (Numeric::Decimal(a), Numeric::Int(b)) => Some(a.cmp(&Decimal::from_int(b))),
+            (Numeric::Int(a), Numeric::Decimal(b)) => Some(Decimal::from_int(a).cmp(&b)),
+            (Numeric::Decimal(a), Numeric::Float(b)) => Some(a.cmp(&Decimal::from_f64(b))),
+            (Numeric::Float(a), Numeric::Decimal(b)) => Some(Decimal::from_f64(a).cmp(&b)),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Self {
+        Number { n: N::PosInt(n) }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        if n >= 0 {
+            Number { n: N::PosInt(n as u64) }
+        } else {
+            Number { n: N::NegInt(n) }
+        }
+    }
+}
+
+impl From<i32> for Number {
+    fn from(n: i32) -> Self {
+        Number::from(n as i64)
+    }
+}
+
+impl From<u32> for Number {
+    fn from(n: u32) -> Self {
+        Number::from(n as u64)
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.n {
+            N::PosInt(n) => write!(f, "{}", n),
+            N::NegInt(n) => write!(f, "{}", n),
+            // Debug formatting gives the shortest text that reads back as
+            // the same f64, switching to exponent notation for large and
+            // small magnitudes.
+            N::Float(n) => write!(f, "{:?}", n),
+            N::Raw(lexeme) => write!(f, "{}", lexeme),
+        }
+    }
+}
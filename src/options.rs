@@ -0,0 +1,39 @@
+//! Settings shared by the lexer and the parser.
+
+/// What to do with a `\uXXXX` escape naming a UTF-16 surrogate that is not
+/// part of a valid pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoneSurrogates {
+    /// Reject the string.
+    #[default]
+    Error,
+    /// Substitute U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+    /// Keep the code unit, encoded as WTF-8. The event parsers report
+    /// strings containing one as [`Event::Wtf8Key`](crate::Event::Wtf8Key)
+    /// or [`Event::Wtf8String`](crate::Event::Wtf8String); they cannot be
+    /// stored in a [`JsonValue`](crate::JsonValue), so parsing one into a
+    /// tree is an error.
+    Preserve,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub lone_surrogates: LoneSurrogates,
+    /// Keep every number's literal text instead of converting it, so that
+    /// values round-trip byte for byte.
+    pub raw_numbers: bool,
+    /// How deeply arrays and objects may nest; opening one more is a
+    /// [`ParseError::TooDeep`](crate::Error::TooDeep). Defaults to 128.
+    pub max_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            lone_surrogates: LoneSurrogates::default(),
+            raw_numbers: false,
+            max_depth: 128,
+        }
+    }
+}
@@ -1,78 +1,347 @@
+//! The shift-reduce driver that turns tokens into a [`JsonValue`].
+
+use std::fmt;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::str::{FromStr, Utf8Error};
+
+use crate::borrowed::{BorrowedValue, Builder};
+use crate::event::{self, Event};
 use crate::unmarshal::JsonValue;
-use crate::lexer::lex;
-use crate::grammar::{StackElement, ElementType};
-use crate::util::{check_prefix_exists, get_value, NOMATCH, PARTIALMATCH};
+use crate::lexer::{Lexer, Span, Token, TokenError, TokenValue};
+use crate::options::ParseOptions;
+use crate::grammar::{StackElement, ElementType, JsonElement, GRAMMAR};
+use crate::table::{tables, Action};
+use crate::util::get_value;
 
-use crate::action::action;
+use crate::action::{accepts, action, expected_tokens, goto};
 
 #[derive(Debug)]
-enum ParseError<'a> {
-    LexingError,
-    ParsingError,
-    UnexpectedToken(ElementType<'a>),
-}
-
-fn parse(input: &str) -> Result<JsonValue, ParseError> {
-    let tokens = match lex(input) {
-        Ok(token) => token,
-        Err(_) => return Err(ParseError::LexingError),
-    };
-
-    let mut stack: Vec<StackElement> = Vec::new();
-    let mut reduced_performed = true;
-
-    for mut i in 0..tokens.len() {
-        let lookahead = &tokens[i];
-        let match_type = check_prefix_exists(stack, *lookahead);
-
-        if match_type != NOMATCH {
-            let next_index = i + 1;
-            stack.push(StackElement{
-                value: Some(lookahead),
-                rule: None
-            });
-            if match_type == PARTIALMATCH {
-                i = next_index;
-                continue;
+pub enum ParseError {
+    LexingError(TokenError),
+    /// The input ended (at `span`) before a complete value was read.
+    ParsingError {
+        span: Span,
+        expected: Vec<ElementType>,
+    },
+    UnexpectedToken {
+        found: ElementType,
+        span: Span,
+        expected: Vec<ElementType>,
+    },
+    /// An array or object opened at `span` is nested more than `limit`
+    /// levels deep.
+    TooDeep {
+        span: Span,
+        limit: usize,
+    },
+    InvalidUtf8(Utf8Error),
+    Io(io::Error),
+}
+
+impl ParseError {
+    /// Where in the input the error was detected, when known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::LexingError(err) => Some(err.span()),
+            ParseError::ParsingError { span, .. } => Some(*span),
+            ParseError::UnexpectedToken { span, .. } => Some(*span),
+            ParseError::TooDeep { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Formats the error followed by the offending line of `input`, with a
+    /// caret under the position of the error.
+    pub fn render(&self, input: &str) -> String {
+        let span = match self.span() {
+            Some(span) => span,
+            None => return self.to_string(),
+        };
+
+        let line = input.lines().nth(span.line - 1).unwrap_or("");
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let indent: String = line
+            .chars()
+            .take(span.column - 1)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "error: {}\n{} |\n{} | {}\n{} | {}^",
+            self, gutter, number, line, gutter, indent
+        )
+    }
+}
+
+fn describe_expected(expected: &[ElementType]) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for name in expected.iter().map(|e| e.describe()) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.split_last() {
+        None => String::new(),
+        Some((last, [])) => format!(", expected {}", last),
+        Some((last, rest)) => format!(", expected one of {} or {}", rest.join(", "), last),
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::LexingError(err) => write!(f, "{}", err),
+            ParseError::ParsingError { span, expected } => {
+                write!(f, "unexpected end of input at {}{}", span, describe_expected(expected))
+            }
+            ParseError::UnexpectedToken { found, span, expected } => {
+                write!(f, "unexpected {} at {}{}", found.describe(), span, describe_expected(expected))
             }
-        } else if !reduced_performed {
-            return Err(ParseError::UnexpectedToken(lookahead.token_type));
+            ParseError::TooDeep { span, limit } => write!(f, "nesting deeper than {} levels at {}", limit, span),
+            ParseError::InvalidUtf8(err) => write!(f, "input is not valid UTF-8: {}", err),
+            ParseError::Io(err) => write!(f, "failed to read input: {}", err),
         }
+    }
+}
 
-        let (json_element, offset) = action(stack); 
-
-        if offset != 0 {
-            stack.truncate(stack.len() - offset as usize);
-            stack.push(StackElement {
-                value: None,
-                rule: Some(json_element),
-            });
-            reduced_performed = true;
-        } else {
-            reduced_performed = false;
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::LexingError(err) => Some(err),
+            ParseError::InvalidUtf8(err) => Some(err),
+            ParseError::Io(err) => Some(err),
+            _ => None,
         }
     }
+}
+
+/// Parses a complete JSON document.
+pub fn parse(input: &str) -> Result<JsonValue, ParseError> {
+    parse_with(input, &ParseOptions::default())
+}
+
+pub fn parse_with(input: &str, options: &ParseOptions) -> Result<JsonValue, ParseError> {
+    parse_tokens(Lexer::with_options(input.as_bytes(), options.clone()), options)
+}
+
+/// Parses a complete JSON document from UTF-8 encoded bytes.
+pub fn parse_bytes(input: &[u8]) -> Result<JsonValue, ParseError> {
+    match std::str::from_utf8(input) {
+        Ok(input) => parse(input),
+        Err(err) => Err(ParseError::InvalidUtf8(err)),
+    }
+}
+
+/// Parses a single JSON document from `reader`, lexing it a buffer at a
+/// time rather than reading it all into memory first.
+pub fn parse_reader<R: Read>(reader: R) -> Result<JsonValue, ParseError> {
+    parse_buf_reader(BufReader::new(reader), &ParseOptions::default())
+}
 
-    loop {
-        let (json_element, offset) = action(stack); 
+pub fn parse_buf_reader<R: BufRead>(reader: R, options: &ParseOptions) -> Result<JsonValue, ParseError> {
+    parse_tokens(Lexer::with_options(reader, options.clone()), options)
+}
+
+pub(crate) fn parse_tokens<R: BufRead>(mut lexer: Lexer<R>, options: &ParseOptions) -> Result<JsonValue, ParseError> {
+    let mut parser = Parser::new(options.clone());
+    for token in &mut lexer {
+        parser.push(token?)?;
+    }
+    parser.finish(lexer.position())
+}
+
+// What the parser makes of the tokens it is fed.
+enum Output<'a> {
+    Tree,
+    // Containers are reported as events instead of being built.
+    Events(VecDeque<Event>),
+    Borrowed(Builder<'a>),
+}
+
+// The shift-reduce loop, fed one token at a time.
+pub(crate) struct Parser<'a> {
+    options: ParseOptions,
+    // `states` always has one more entry than `stack`: the state the
+    // parser was in before anything was shifted.
+    states: Vec<usize>,
+    stack: Vec<StackElement>,
+    // The number of arrays and objects currently open.
+    depth: usize,
+    output: Output<'a>,
+}
 
-        if offset != 0 {
-            stack.truncate(stack.len() - offset as usize);
-            stack.push(StackElement {
-                value: None,
-                rule: Some(json_element),
-            });
-        } else {
-            break;
+impl<'a> Parser<'a> {
+    pub(crate) fn new(options: ParseOptions) -> Self {
+        Parser {
+            options,
+            states: vec![0],
+            stack: Vec::new(),
+            depth: 0,
+            output: Output::Tree,
         }
     }
 
-    if stack.len() != 1 {
-        return Err(ParseError::ParsingError);
+    pub(crate) fn with_events(options: ParseOptions) -> Self {
+        Parser {
+            output: Output::Events(VecDeque::new()),
+            ..Parser::new(options)
+        }
     }
 
-    let val = get_value(stack);
+    // Builds a `BorrowedValue` of `input`, which the tokens must be lexed
+    // from by a lexer borrowing strings.
+    pub(crate) fn borrowing(options: ParseOptions, input: &'a str) -> Self {
+        Parser {
+            output: Output::Borrowed(Builder::new(input)),
+            ..Parser::new(options)
+        }
+    }
+
+    pub(crate) fn is_emitting_events(&self) -> bool {
+        matches!(self.output, Output::Events(_))
+    }
+
+    // Whether the tokens pushed so far make up a whole document.
+    pub(crate) fn is_complete(&self) -> bool {
+        !self.stack.is_empty() && accepts(&self.states, ElementType::End)
+    }
+
+    pub(crate) fn next_event(&mut self) -> Option<Event> {
+        match &mut self.output {
+            Output::Events(events) => events.pop_front(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn take_borrowed(&mut self) -> Option<BorrowedValue<'a>> {
+        match &mut self.output {
+            Output::Borrowed(builder) => builder.take(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, lookahead: Token) -> Result<(), ParseError> {
+        if matches!(lookahead.value, TokenValue::Wtf8(_)) && !self.is_emitting_events() {
+            let msg = "string with a lone surrogate cannot be stored in a JsonValue".to_string();
+            return Err(ParseError::LexingError(TokenError::StringLexFailure(msg, lookahead.span)));
+        }
+
+        loop {
+            let state = self.states[self.states.len() - 1];
+            match action(state, lookahead.token_type) {
+                Action::Shift(next) => {
+                    match lookahead.token_type {
+                        ElementType::ObjectStart | ElementType::ArrayStart => {
+                            if self.depth == self.options.max_depth {
+                                return Err(ParseError::TooDeep { span: lookahead.span, limit: self.options.max_depth });
+                            }
+                            self.depth += 1;
+                        }
+                        ElementType::ObjectEnd | ElementType::ArrayEnd => self.depth -= 1,
+                        _ => {}
+                    }
+                    match &mut self.output {
+                        Output::Tree => {}
+                        Output::Events(events) => event::shift(&lookahead, &self.stack, events),
+                        Output::Borrowed(builder) => builder.shift(&lookahead, &mut self.stack),
+                    }
+                    self.states.push(next);
+                    self.stack.push(StackElement {
+                        value: Some(lookahead),
+                        rule: None,
+                    });
+                    return Ok(());
+                }
+                Action::Reduce(production) => self.reduce(production),
+                Action::Accept | Action::Error => return Err(self.unexpected(&lookahead)),
+            }
+        }
+    }
 
-    Ok(JsonValue::new(val, "nil"))
+    // Whether `terminal` can be pushed next without an error.
+    pub(crate) fn accepts(&self, terminal: ElementType) -> bool {
+        accepts(&self.states, terminal)
+    }
+
+    pub(crate) fn unexpected(&self, token: &Token) -> ParseError {
+        ParseError::UnexpectedToken {
+            found: token.token_type,
+            span: token.span,
+            expected: expected_tokens(&self.states),
+        }
+    }
+
+    // Ends the input at `end`, returning the document if it is complete.
+    pub(crate) fn finish(&mut self, end: Span) -> Result<JsonValue, ParseError> {
+        loop {
+            let state = self.states[self.states.len() - 1];
+            match action(state, ElementType::End) {
+                Action::Accept => break,
+                Action::Reduce(production) => self.reduce(production),
+                Action::Shift(_) | Action::Error => {
+                    return Err(ParseError::ParsingError {
+                        span: end,
+                        expected: expected_tokens(&self.states),
+                    });
+                }
+            }
+        }
+
+        self.states.truncate(1);
+        match get_value(std::mem::take(&mut self.stack)) {
+            Ok(val) => Ok(val),
+            Err(_) => Err(ParseError::ParsingError {
+                span: end,
+                expected: Vec::new(),
+            }),
+        }
+    }
+
+    // Replaces the right-hand side of `production` on top of the stack with
+    // the value its rule builds, and moves to the GOTO state for its lhs.
+    fn reduce(&mut self, production: usize) {
+        let production = &tables().productions[production];
+        let rule = &GRAMMAR[production.rule.expect("the start production is accepted, not reduced")];
+
+        let start = self.stack.len() - production.rhs.len();
+        let values = &mut self.stack[start..];
+        let value = match &mut self.output {
+            Output::Tree => (rule.to_json)(values, &self.options),
+            Output::Events(events) => event::reduce(rule, values, &self.options, events),
+            Output::Borrowed(builder) => builder.reduce(rule, values, &self.options),
+        };
+
+        self.stack.truncate(start);
+        self.states.truncate(start + 1);
+        self.stack.push(StackElement {
+            value: None,
+            rule: Some(JsonElement {
+                value,
+                element_type: rule.lhs,
+            }),
+        });
+
+        let state = self.states[self.states.len() - 1];
+        let next = goto(state, rule.lhs).expect("GOTO is defined after every reduction");
+        self.states.push(next);
+    }
 }
 
+impl From<TokenError> for ParseError {
+    fn from(err: TokenError) -> Self {
+        match err {
+            TokenError::Io(err, _) => ParseError::Io(err),
+            err => ParseError::LexingError(err),
+        }
+    }
+}
+
+impl FromStr for JsonValue {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
@@ -0,0 +1,142 @@
+//! JSON Patch ([RFC 6902]) and JSON Merge Patch ([RFC 7396]).
+//!
+//! [RFC 6902]: https://www.rfc-editor.org/rfc/rfc6902
+//! [RFC 7396]: https://www.rfc-editor.org/rfc/rfc7396
+
+use std::fmt;
+
+use crate::pointer::PointerError;
+use crate::unmarshal::{JsonValue, Map};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The patch is not an array of operations (`index` is `None`), or the
+    /// operation at `index` is missing a member or has an unknown `op`.
+    Invalid { index: Option<usize>, message: String },
+    /// The operation at `index` refers to a location that does not exist.
+    Pointer { index: usize, error: PointerError },
+    /// The `test` operation at `index` found a different value at `path`.
+    TestFailed { index: usize, path: String },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Invalid { index: None, message } => write!(f, "invalid patch: {}", message),
+            PatchError::Invalid { index: Some(index), message } => write!(f, "operation {}: {}", index, message),
+            PatchError::Pointer { index, error } => write!(f, "operation {}: {}", index, error),
+            PatchError::TestFailed { index, path } => write!(f, "operation {}: test failed at {:?}", index, path),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchError::Pointer { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Applies the RFC 6902 `patch`, an array of operations, to `doc`. The
+/// operations are applied in order; if any fails, `doc` is left unchanged.
+pub fn apply_patch(doc: &mut JsonValue, patch: &JsonValue) -> Result<(), PatchError> {
+    let operations = patch.as_array().ok_or_else(|| PatchError::Invalid {
+        index: None,
+        message: format!("expected an array of operations, found {}", patch.type_name()),
+    })?;
+
+    let mut patched = doc.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut patched, operation, index)?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+fn apply_operation(doc: &mut JsonValue, operation: &JsonValue, index: usize) -> Result<(), PatchError> {
+    let invalid = |message: String| PatchError::Invalid { index: Some(index), message };
+    let pointer = |error: PointerError| PatchError::Pointer { index, error };
+
+    let operation = operation
+        .as_object()
+        .ok_or_else(|| invalid(format!("expected an object, found {}", operation.type_name())))?;
+    let string = |name: &str| match operation.get(name) {
+        Some(JsonValue::String(s)) => Ok(s.as_str()),
+        Some(other) => Err(invalid(format!("`{}` must be a string, found {}", name, other.type_name()))),
+        None => Err(invalid(format!("missing `{}`", name))),
+    };
+    let value = || operation.get("value").cloned().ok_or_else(|| invalid("missing `value`".to_string()));
+
+    let op = string("op")?;
+    let path = string("path")?;
+    match op {
+        "add" => {
+            doc.pointer_insert(path, value()?).map_err(pointer)?;
+        }
+        "remove" => {
+            doc.pointer_remove(path).map_err(pointer)?;
+        }
+        "replace" => {
+            *doc.pointer_mut(path).map_err(pointer)? = value()?;
+        }
+        "move" => {
+            let from = string("from")?;
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(invalid(format!("cannot move {:?} into its own child {:?}", from, path)));
+            }
+            if from == path {
+                doc.pointer(from).map_err(pointer)?;
+                return Ok(());
+            }
+            let moved = doc.pointer_remove(from).map_err(pointer)?;
+            doc.pointer_insert(path, moved).map_err(pointer)?;
+        }
+        "copy" => {
+            let copied = doc.pointer(string("from")?).map_err(pointer)?.clone();
+            doc.pointer_insert(path, copied).map_err(pointer)?;
+        }
+        "test" => {
+            if *doc.pointer(path).map_err(pointer)? != value()? {
+                return Err(PatchError::TestFailed { index, path: path.to_string() });
+            }
+        }
+        _ => return Err(invalid(format!("unknown op {:?}", op))),
+    }
+    Ok(())
+}
+
+/// Applies the RFC 7396 merge `patch` to `doc`: object members of the patch
+/// are merged recursively, `null` members remove the key, and anything else
+/// replaces the target.
+pub fn merge_patch(doc: &mut JsonValue, patch: &JsonValue) {
+    let members = match patch {
+        JsonValue::Object(members) => members,
+        _ => {
+            *doc = patch.clone();
+            return;
+        }
+    };
+    if !matches!(doc, JsonValue::Object(_)) {
+        *doc = JsonValue::Object(Map::new());
+    }
+    let JsonValue::Object(target) = doc else {
+        return;
+    };
+
+    for (key, value) in members.iter() {
+        if value.is_null() {
+            target.remove(key);
+            continue;
+        }
+        match target.get_mut(key) {
+            Some(existing) => merge_patch(existing, value),
+            None => {
+                let mut merged = JsonValue::Null;
+                merge_patch(&mut merged, value);
+                target.insert(key.clone(), merged);
+            }
+        }
+    }
+}
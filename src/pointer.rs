@@ -0,0 +1,192 @@
+//! JSON Pointer ([RFC 6901]) lookup and mutation on [`JsonValue`].
+//!
+//! [RFC 6901]: https://www.rfc-editor.org/rfc/rfc6901
+
+use std::fmt;
+
+use crate::unmarshal::JsonValue;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// The pointer is neither empty nor starts with `/`, or has a `~` that
+    /// is not followed by `0` or `1`.
+    Syntax(String),
+    /// The object at `at` has no member `key`.
+    MissingKey { at: String, key: String },
+    /// `token` is not a valid index into the array at `at`, or is past its
+    /// end.
+    BadIndex { at: String, token: String, len: usize },
+    /// The value at `at` is a scalar, so it has no children.
+    NotAContainer { at: String, found: &'static str },
+    /// The empty pointer refers to the whole document, which cannot be
+    /// removed.
+    RemoveRoot,
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerError::Syntax(pointer) => write!(f, "invalid JSON pointer {:?}", pointer),
+            PointerError::MissingKey { at, key } => write!(f, "no member {:?} in the object at {:?}", key, at),
+            PointerError::BadIndex { at, token, len } => {
+                write!(f, "no index {:?} in the array of length {} at {:?}", token, len, at)
+            }
+            PointerError::NotAContainer { at, found } => write!(f, "the value at {:?} is a {}", at, found),
+            PointerError::RemoveRoot => write!(f, "cannot remove the whole document"),
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// Escapes `key` for use as one token of a pointer.
+pub fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Splits `pointer` into its unescaped reference tokens.
+pub fn tokens(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/').ok_or_else(|| PointerError::Syntax(pointer.to_string()))?;
+    rest.split('/')
+        .map(|token| {
+            let mut out = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    out.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => out.push('~'),
+                    Some('1') => out.push('/'),
+                    _ => return Err(PointerError::Syntax(pointer.to_string())),
+                }
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
+// The pointer to the parent of `tokens[i]`, for error messages.
+fn prefix(tokens: &[String], i: usize) -> String {
+    tokens[..i].iter().map(|token| format!("/{}", escape(token))).collect()
+}
+
+// Indexes are decimal with no leading zeros; `-` is the position after the
+// last element.
+fn index(token: &str, len: usize, allow_end: bool) -> Option<usize> {
+    if token == "-" {
+        return allow_end.then_some(len);
+    }
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    let i: usize = token.parse().ok()?;
+    (i < len || (allow_end && i == len)).then_some(i)
+}
+
+fn step<'v>(value: &'v JsonValue, tokens: &[String], i: usize) -> Result<&'v JsonValue, PointerError> {
+    let token = &tokens[i];
+    match value {
+        JsonValue::Object(o) => o.get(token).ok_or_else(|| PointerError::MissingKey {
+            at: prefix(tokens, i),
+            key: token.clone(),
+        }),
+        JsonValue::Array(a) => index(token, a.len(), false).map(|i| &a[i]).ok_or_else(|| PointerError::BadIndex {
+            at: prefix(tokens, i),
+            token: token.clone(),
+            len: a.len(),
+        }),
+        _ => Err(PointerError::NotAContainer { at: prefix(tokens, i), found: value.type_name() }),
+    }
+}
+
+fn step_mut<'v>(value: &'v mut JsonValue, tokens: &[String], i: usize) -> Result<&'v mut JsonValue, PointerError> {
+    let token = &tokens[i];
+    let found = value.type_name();
+    match value {
+        JsonValue::Object(o) => o.get_mut(token).ok_or_else(|| PointerError::MissingKey {
+            at: prefix(tokens, i),
+            key: token.clone(),
+        }),
+        JsonValue::Array(a) => {
+            let len = a.len();
+            match index(token, len, false) {
+                Some(i) => Ok(&mut a[i]),
+                None => Err(PointerError::BadIndex { at: prefix(tokens, i), token: token.clone(), len }),
+            }
+        }
+        _ => Err(PointerError::NotAContainer { at: prefix(tokens, i), found }),
+    }
+}
+
+fn resolve_mut<'v>(mut value: &'v mut JsonValue, tokens: &[String]) -> Result<&'v mut JsonValue, PointerError> {
+    for i in 0..tokens.len() {
+        value = step_mut(value, tokens, i)?;
+    }
+    Ok(value)
+}
+
+impl JsonValue {
+    /// Looks up the value `pointer` refers to, e.g. `"/a/0/b"`.
+    pub fn pointer(&self, pointer: &str) -> Result<&JsonValue, PointerError> {
+        let tokens = tokens(pointer)?;
+        let mut value = self;
+        for i in 0..tokens.len() {
+            value = step(value, &tokens, i)?;
+        }
+        Ok(value)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonValue, PointerError> {
+        resolve_mut(self, &tokens(pointer)?)
+    }
+
+    /// Sets the value `pointer` refers to, whose parent must exist. An object
+    /// member is added or replaced; into an array the value is inserted at
+    /// the index, shifting later elements, or appended for `-`. Returns the
+    /// replaced value, if any.
+    pub fn pointer_insert(&mut self, pointer: &str, value: JsonValue) -> Result<Option<JsonValue>, PointerError> {
+        let tokens = tokens(pointer)?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Ok(Some(std::mem::replace(self, value))),
+        };
+        let parent = resolve_mut(self, parents)?;
+        let found = parent.type_name();
+        match parent {
+            JsonValue::Object(o) => Ok(o.insert(last.clone(), value)),
+            JsonValue::Array(a) => match index(last, a.len(), true) {
+                Some(i) => {
+                    a.insert(i, value);
+                    Ok(None)
+                }
+                None => Err(PointerError::BadIndex { at: prefix(&tokens, parents.len()), token: last.clone(), len: a.len() }),
+            },
+            _ => Err(PointerError::NotAContainer { at: prefix(&tokens, parents.len()), found }),
+        }
+    }
+
+    /// Removes and returns the value `pointer` refers to; later array
+    /// elements shift down.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<JsonValue, PointerError> {
+        let tokens = tokens(pointer)?;
+        let (last, parents) = tokens.split_last().ok_or(PointerError::RemoveRoot)?;
+        let parent = resolve_mut(self, parents)?;
+        let found = parent.type_name();
+        match parent {
+            JsonValue::Object(o) => o.remove(last).ok_or_else(|| PointerError::MissingKey {
+                at: prefix(&tokens, parents.len()),
+                key: last.clone(),
+            }),
+            JsonValue::Array(a) => match index(last, a.len(), false) {
+                Some(i) => Ok(a.remove(i)),
+                None => Err(PointerError::BadIndex { at: prefix(&tokens, parents.len()), token: last.clone(), len: a.len() }),
+            },
+            _ => Err(PointerError::NotAContainer { at: prefix(&tokens, parents.len()), found }),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! A parser that is handed its input a piece at a time, for documents that
+//! arrive in arbitrary fragments, e.g. over a socket.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
+
+use crate::event::Event;
+use crate::grammar::ElementType;
+use crate::lexer::{is_whitespace, Lexer, PartialString, Span, Token};
+use crate::options::ParseOptions;
+use crate::parse::{ParseError, Parser};
+use crate::unmarshal::JsonValue;
+
+/// Parses a document fed to it with [`feed`](PushParser::feed), in chunks
+/// that may split tokens anywhere. Input is parsed as soon as it arrives:
+/// the document is available from [`next_value`](PushParser::next_value)
+/// (or, for a parser made with [`with_events`](PushParser::with_events), its
+/// events from [`next_event`](PushParser::next_event)) once it is complete,
+/// which for a top-level number is not known until
+/// [`finish`](PushParser::finish).
+///
+/// The parser should not be used after it has returned an error.
+pub struct PushParser {
+    options: ParseOptions,
+    parser: Parser<'static>,
+    // Input after the last complete token, i.e. the start of a token that
+    // may continue in the next chunk.
+    buffer: Vec<u8>,
+    position: Span,
+    // A string cut off by the end of the input so far, which the buffer
+    // continues: it is carried on from there rather than lexed again.
+    string: Option<PartialString>,
+    // The buffered token is a number, so there is no point in lexing it
+    // again until something other than a number character arrives.
+    in_number: bool,
+    complete: bool,
+    values: VecDeque<JsonValue>,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        PushParser::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        PushParser::with_parser(Parser::new(options.clone()), options)
+    }
+
+    /// A parser that reports the document as [`Event`]s instead of building
+    /// a value.
+    pub fn with_events(options: ParseOptions) -> Self {
+        PushParser::with_parser(Parser::with_events(options.clone()), options)
+    }
+
+    fn with_parser(parser: Parser<'static>, options: ParseOptions) -> Self {
+        PushParser {
+            options,
+            parser,
+            buffer: Vec::new(),
+            position: Span::start(),
+            string: None,
+            in_number: false,
+            complete: false,
+            values: VecDeque::new(),
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), ParseError> {
+        let skip = self.in_number && bytes.iter().all(|&b| is_number_byte(b));
+        self.buffer.extend_from_slice(bytes);
+        if skip {
+            return Ok(());
+        }
+        self.lex(false)
+    }
+
+    /// Ends the input, failing if the document is truncated.
+    pub fn finish(&mut self) -> Result<(), ParseError> {
+        self.lex(true)?;
+        if !self.complete {
+            self.complete(self.position)?;
+        }
+        Ok(())
+    }
+
+    pub fn next_value(&mut self) -> Option<JsonValue> {
+        self.values.pop_front()
+    }
+
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.parser.next_event()
+    }
+
+    // Lexes and parses the buffered input. A token that runs into the end
+    // of the buffer stays there until more input arrives, unless `last`.
+    fn lex(&mut self, last: bool) -> Result<(), ParseError> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let chunk = Chunk { data: &buffer, hit_end: false };
+        let mut lexer = Lexer::resume(chunk, self.options.clone(), self.position).continuing(self.string.take());
+        if !last {
+            lexer = lexer.suspending_strings();
+        }
+        // The tokens of the lexeme being read: one, or several for a number.
+        let mut tokens = Vec::new();
+        let mut start = self.position;
+
+        loop {
+            if tokens.is_empty() {
+                start = lexer.position();
+                lexer.reader_mut().hit_end = false;
+            }
+            let token = match lexer.next() {
+                Some(token) => token,
+                None => {
+                    start = lexer.position();
+                    break;
+                }
+            };
+
+            let partial = lexer.reader_mut().hit_end && !last;
+            match token {
+                Ok(token) => tokens.push(token),
+                Err(_) if lexer.has_partial_string() => {
+                    self.string = lexer.take_partial_string();
+                    start = lexer.position();
+                    break;
+                }
+                Err(_) if partial => break,
+                Err(err) => return Err(err.into()),
+            }
+            if !lexer.is_between_tokens() {
+                continue;
+            }
+            if partial {
+                break;
+            }
+
+            for token in tokens.drain(..) {
+                self.shift(token)?;
+            }
+            if !self.complete && self.parser.is_complete() {
+                self.complete(lexer.position())?;
+            }
+        }
+
+        self.buffer = buffer;
+        self.buffer.drain(..start.offset - self.position.offset);
+        self.position = start;
+        self.in_number = self.string.is_none()
+            && matches!(self.buffer.iter().find(|&&b| !is_whitespace(b)), Some(b'-' | b'0'..=b'9'));
+        Ok(())
+    }
+
+    fn shift(&mut self, token: Token) -> Result<(), ParseError> {
+        if self.complete {
+            return Err(ParseError::UnexpectedToken {
+                found: token.token_type,
+                span: token.span,
+                expected: vec![ElementType::End],
+            });
+        }
+        self.parser.push(token)
+    }
+
+    fn complete(&mut self, end: Span) -> Result<(), ParseError> {
+        let value = self.parser.finish(end)?;
+        if !self.parser.is_emitting_events() {
+            self.values.push_back(value);
+        }
+        self.complete = true;
+        Ok(())
+    }
+}
+
+fn is_number_byte(b: u8) -> bool {
+    matches!(b, b'0'..=b'9' | b'+' | b'-' | b'.' | b'e' | b'E')
+}
+
+// The unlexed input, noting whether the lexer ran into its end: a token
+// that did may continue in the next chunk.
+struct Chunk<'a> {
+    data: &'a [u8],
+    hit_end: bool,
+}
+
+impl Read for Chunk<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for Chunk<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.data.is_empty() {
+            self.hit_end = true;
+        }
+        Ok(self.data)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.data = &self.data[amt..];
+    }
+}
@@ -0,0 +1,233 @@
+//! Reading a stream of JSON documents rather than a single one: newline
+//! delimited JSON (NDJSON, JSON Lines) with [`Lines`], or values simply
+//! written one after the other with [`Documents`].
+//!
+//! A malformed record is reported with the line it starts on and skipped;
+//! the documents after it are still read.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::BufRead;
+
+use crate::lexer::{is_whitespace, Lexer, Span, Token};
+use crate::options::ParseOptions;
+use crate::parse::{parse_tokens, ParseError, Parser};
+use crate::unmarshal::JsonValue;
+
+#[derive(Debug)]
+pub struct RecordError {
+    /// The line the failing record starts on.
+    pub line: usize,
+    pub error: ParseError,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record on line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// One document per line. Blank lines are skipped.
+pub struct Lines<R> {
+    reader: R,
+    options: ParseOptions,
+    position: Span,
+    line: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> Lines<R> {
+    pub fn new(reader: R) -> Self {
+        Lines::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Lines {
+            reader,
+            options,
+            position: Span::start(),
+            line: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = Result<JsonValue, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let start = self.position;
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(n) => {
+                    self.position = Span { offset: start.offset + n, line: start.line + 1, column: 1 };
+                    if self.line.iter().all(|&b| is_whitespace(b)) {
+                        continue;
+                    }
+                    let text = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+                    let lexer = Lexer::resume(text, self.options.clone(), start);
+                    let result = parse_tokens(lexer, &self.options);
+                    return Some(result.map_err(|error| RecordError { line: start.line, error }));
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(RecordError { line: start.line, error: ParseError::Io(err) }));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Documents written one after the other, separated by whitespace where
+/// they would otherwise run together (as two numbers would). A document ends
+/// as soon as the next token cannot continue it.
+///
+/// After an error, reading resumes with the next token at the very start of
+/// a line that can begin a document, which is where documents written one
+/// per line or pretty-printed begin.
+pub struct Documents<R> {
+    lexer: Lexer<R>,
+    options: ParseOptions,
+    parser: Parser<'static>,
+    // The line the current document starts on, once it has.
+    start: Option<usize>,
+    // The first token of the next document, read while ending this one.
+    next: Option<Token>,
+    // Set after an error, until the next token in column 1.
+    resync: bool,
+    // Errors to report before reading on.
+    errors: VecDeque<RecordError>,
+    done: bool,
+}
+
+impl<R: BufRead> Documents<R> {
+    pub fn new(reader: R) -> Self {
+        Documents::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Documents {
+            lexer: Lexer::with_options(reader, options.clone()),
+            parser: Parser::new(options.clone()),
+            options,
+            start: None,
+            next: None,
+            resync: false,
+            errors: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    // Ends the current document, returning what to report for it.
+    fn end(&mut self, result: Result<JsonValue, ParseError>) -> Result<JsonValue, RecordError> {
+        self.parser = Parser::new(self.options.clone());
+        let start = self.start.take();
+        result.map_err(|error| {
+            self.resync = true;
+            RecordError {
+                line: start.or(error.span().map(|span| span.line)).unwrap_or(1),
+                error,
+            }
+        })
+    }
+
+    // Reads the next token, or returns what to report instead.
+    fn token(&mut self) -> Result<Token, Option<Result<JsonValue, RecordError>>> {
+        loop {
+            let error = match self.lexer.next() {
+                // The parser is fresh while resyncing, so this also skips the
+                // closing brackets of the document that failed.
+                Some(Ok(token)) if self.resync && (token.span.column != 1 || !self.parser.accepts(token.token_type)) => {
+                    continue
+                }
+                Some(Ok(token)) => {
+                    self.resync = false;
+                    return Ok(token);
+                }
+                Some(Err(err)) => ParseError::from(err),
+                None => {
+                    self.done = true;
+                    if self.start.is_none() {
+                        return Err(None);
+                    }
+                    let result = self.parser.finish(self.lexer.position());
+                    return Err(Some(self.end(result)));
+                }
+            };
+
+            if let ParseError::Io(_) = error {
+                self.done = true;
+            } else if let Err(err) = self.lexer.skip_line() {
+                self.done = true;
+                let line = self.lexer.position().line;
+                self.errors.push_back(RecordError { line, error: err.into() });
+            }
+            if self.resync {
+                continue;
+            }
+
+            // A document the error follows is complete all the same.
+            if self.start.is_some() && self.parser.is_complete() {
+                let result = self.parser.finish(self.lexer.position());
+                let line = error.span().map_or(1, |span| span.line);
+                self.errors.push_front(RecordError { line, error });
+                self.resync = true;
+                return Err(Some(self.end(result)));
+            }
+            return Err(Some(self.end(Err(error))));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Documents<R> {
+    type Item = Result<JsonValue, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(err) = self.errors.pop_front() {
+                return Some(Err(err));
+            }
+
+            let token = match self.next.take() {
+                Some(token) => {
+                    self.resync = false;
+                    token
+                }
+                None if self.done => return None,
+                None => match self.token() {
+                    Ok(token) => token,
+                    Err(None) => continue,
+                    Err(Some(result)) => return Some(result),
+                },
+            };
+
+            if self.start.is_some() && self.parser.is_complete() && !self.parser.accepts(token.token_type) {
+                let result = self.parser.finish(token.span);
+                self.next = Some(token);
+                return Some(self.end(result));
+            }
+
+            let start = *self.start.get_or_insert(token.span.line);
+            if !self.parser.accepts(token.token_type) {
+                let error = self.parser.unexpected(&token);
+                // The token may begin the next document.
+                if token.span.column == 1 && token.span.line > start {
+                    self.next = Some(token);
+                }
+                return Some(self.end(Err(error)));
+            }
+            if let Err(error) = self.parser.push(token) {
+                return Some(self.end(Err(error)));
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! LALR(1) ACTION/GOTO tables compiled once from [`GRAMMAR`].
+//!
+//! The LR(0) automaton is built first; lookaheads are then propagated
+//! between the kernel items of its states until they stop changing, which
+//! gives the same tables as merging the canonical LR(1) states with equal
+//! cores.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+use crate::grammar::{ElementType, GRAMMAR};
+
+/// Left-hand side of the augmented production `<start> -> <value>`.
+pub const START: ElementType = ElementType::Start;
+
+static START_RHS: [ElementType; 1] = [ElementType::Value];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Production {
+    pub lhs: ElementType,
+    pub rhs: &'static [ElementType],
+    /// Index of the rule in `GRAMMAR`; `None` for the augmented production.
+    pub rule: Option<usize>,
+}
+
+/// A production with a dot before `rhs[dot]`.
+pub type Item = (usize, usize);
+
+pub struct Tables {
+    pub terminals: Vec<ElementType>,
+    pub productions: Vec<Production>,
+    /// Indexed by state, then terminal.
+    pub action: Vec<Vec<Action>>,
+    /// Indexed by state, then non-terminal.
+    pub goto: Vec<Vec<Option<usize>>>,
+    terminal_index: HashMap<ElementType, usize>,
+    nonterminal_index: HashMap<ElementType, usize>,
+}
+
+impl Tables {
+    pub fn terminal(&self, symbol: ElementType) -> Option<usize> {
+        self.terminal_index.get(&symbol).copied()
+    }
+
+    pub fn nonterminal(&self, symbol: ElementType) -> Option<usize> {
+        self.nonterminal_index.get(&symbol).copied()
+    }
+}
+
+pub fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(build)
+}
+
+// Symbols and productions of the augmented grammar, plus the FIRST sets
+// the item closures need.
+pub(crate) struct Grammar {
+    pub terminals: Vec<ElementType>,
+    pub nonterminals: Vec<ElementType>,
+    pub productions: Vec<Production>,
+    pub nullable: Vec<bool>,
+    pub first: Vec<BTreeSet<usize>>,
+    terminal_index: HashMap<ElementType, usize>,
+    nonterminal_index: HashMap<ElementType, usize>,
+}
+
+impl Grammar {
+    pub fn new() -> Self {
+        let mut nonterminals = vec![START];
+        for rule in GRAMMAR.iter() {
+            if !nonterminals.contains(&rule.lhs) {
+                nonterminals.push(rule.lhs);
+            }
+        }
+
+        let mut productions = vec![Production { lhs: START, rhs: &START_RHS, rule: None }];
+        let mut terminals = Vec::new();
+        for (index, rule) in GRAMMAR.iter().enumerate() {
+            for rhs in rule.rhs {
+                for symbol in rhs.iter() {
+                    if !nonterminals.contains(symbol) && !terminals.contains(symbol) {
+                        terminals.push(*symbol);
+                    }
+                }
+                productions.push(Production { lhs: rule.lhs, rhs, rule: Some(index) });
+            }
+        }
+        terminals.push(ElementType::End);
+
+        let terminal_index = terminals.iter().enumerate().map(|(i, t)| (*t, i)).collect();
+        let nonterminal_index = nonterminals.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut grammar = Grammar {
+            nullable: vec![false; nonterminals.len()],
+            first: vec![BTreeSet::new(); nonterminals.len()],
+            terminals,
+            nonterminals,
+            productions,
+            terminal_index,
+            nonterminal_index,
+        };
+        grammar.compute_first();
+        grammar
+    }
+
+    pub fn terminal(&self, symbol: ElementType) -> Option<usize> {
+        self.terminal_index.get(&symbol).copied()
+    }
+
+    pub fn nonterminal(&self, symbol: ElementType) -> Option<usize> {
+        self.nonterminal_index.get(&symbol).copied()
+    }
+
+    fn compute_first(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                let lhs = self.nonterminal_index[&production.lhs];
+                let (first, nullable) = self.first_of(production.rhs);
+                if nullable && !self.nullable[lhs] {
+                    self.nullable[lhs] = true;
+                    changed = true;
+                }
+                let before = self.first[lhs].len();
+                self.first[lhs].extend(first);
+                changed |= self.first[lhs].len() != before;
+            }
+        }
+    }
+
+    /// FIRST of a sequence of symbols, and whether the sequence is nullable.
+    pub fn first_of(&self, symbols: &[ElementType]) -> (BTreeSet<usize>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            match self.nonterminal(*symbol) {
+                Some(n) => {
+                    first.extend(&self.first[n]);
+                    if !self.nullable[n] {
+                        return (first, false);
+                    }
+                }
+                None => {
+                    first.insert(self.terminal_index[symbol]);
+                    return (first, false);
+                }
+            }
+        }
+        (first, true)
+    }
+
+    pub fn next_symbol(&self, (production, dot): Item) -> Option<ElementType> {
+        self.productions[production].rhs.get(dot).copied()
+    }
+
+    /// Adds the LR(1) closure of `items` to it.
+    pub fn closure(&self, items: &mut BTreeMap<Item, BTreeSet<usize>>) {
+        let mut work: Vec<Item> = items.keys().copied().collect();
+
+        while let Some(item) = work.pop() {
+            let n = match self.next_symbol(item).and_then(|s| self.nonterminal(s)) {
+                Some(n) => n,
+                None => continue,
+            };
+
+            let rest = &self.productions[item.0].rhs[item.1 + 1..];
+            let (mut lookahead, nullable) = self.first_of(rest);
+            if nullable {
+                lookahead.extend(&items[&item]);
+            }
+
+            for (p, production) in self.productions.iter().enumerate() {
+                if production.lhs != self.nonterminals[n] {
+                    continue;
+                }
+                let is_new = !items.contains_key(&(p, 0));
+                let entry = items.entry((p, 0)).or_default();
+                let before = entry.len();
+                entry.extend(&lookahead);
+                if is_new || entry.len() != before {
+                    work.push((p, 0));
+                }
+            }
+        }
+    }
+}
+
+// The LR(0) automaton with LALR(1) lookaheads on each kernel item.
+pub(crate) struct Automaton {
+    pub kernels: Vec<BTreeMap<Item, BTreeSet<usize>>>,
+    pub transitions: Vec<BTreeMap<ElementType, usize>>,
+}
+
+impl Automaton {
+    pub fn new(grammar: &Grammar) -> Self {
+        let mut kernels: Vec<Vec<Item>> = vec![vec![(0, 0)]];
+        let mut transitions: Vec<BTreeMap<ElementType, usize>> = Vec::new();
+
+        let mut state = 0;
+        while state < kernels.len() {
+            let mut items: BTreeMap<Item, BTreeSet<usize>> =
+                kernels[state].iter().map(|item| (*item, BTreeSet::new())).collect();
+            grammar.closure(&mut items);
+
+            let mut successors: BTreeMap<ElementType, Vec<Item>> = BTreeMap::new();
+            for item in items.keys() {
+                if let Some(symbol) = grammar.next_symbol(*item) {
+                    successors.entry(symbol).or_default().push((item.0, item.1 + 1));
+                }
+            }
+
+            let mut edges = BTreeMap::new();
+            for (symbol, kernel) in successors {
+                let target = match kernels.iter().position(|k| *k == kernel) {
+                    Some(target) => target,
+                    None => {
+                        kernels.push(kernel);
+                        kernels.len() - 1
+                    }
+                };
+                edges.insert(symbol, target);
+            }
+            transitions.push(edges);
+            state += 1;
+        }
+
+        let mut kernels: Vec<BTreeMap<Item, BTreeSet<usize>>> = kernels
+            .into_iter()
+            .map(|kernel| kernel.into_iter().map(|item| (item, BTreeSet::new())).collect())
+            .collect();
+        kernels[0].get_mut(&(0, 0)).unwrap().insert(grammar.terminal_index[&ElementType::End]);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in 0..kernels.len() {
+                let mut items = kernels[state].clone();
+                grammar.closure(&mut items);
+
+                for (item, lookahead) in items {
+                    let symbol = match grammar.next_symbol(item) {
+                        Some(symbol) => symbol,
+                        None => continue,
+                    };
+                    let target = transitions[state][&symbol];
+                    let entry = kernels[target].get_mut(&(item.0, item.1 + 1)).unwrap();
+                    let before = entry.len();
+                    entry.extend(lookahead);
+                    changed |= entry.len() != before;
+                }
+            }
+        }
+
+        Automaton { kernels, transitions }
+    }
+
+    pub fn items(&self, grammar: &Grammar, state: usize) -> BTreeMap<Item, BTreeSet<usize>> {
+        let mut items = self.kernels[state].clone();
+        grammar.closure(&mut items);
+        items
+    }
+}
+
+// Conflicts are resolved in favour of shifting, then of the production that
+// comes first in GRAMMAR.
+fn resolve(current: Action, candidate: Action) -> Action {
+    match (current, candidate) {
+        (Action::Error, _) => candidate,
+        (Action::Reduce(a), Action::Reduce(b)) => Action::Reduce(a.min(b)),
+        (Action::Reduce(_), _) => candidate,
+        _ => current,
+    }
+}
+
+fn build() -> Tables {
+    let grammar = Grammar::new();
+    let automaton = Automaton::new(&grammar);
+    let states = automaton.kernels.len();
+
+    let mut action = vec![vec![Action::Error; grammar.terminals.len()]; states];
+    let mut goto = vec![vec![None; grammar.nonterminals.len()]; states];
+
+    for state in 0..states {
+        for (item, lookahead) in automaton.items(&grammar, state) {
+            match grammar.next_symbol(item) {
+                Some(symbol) => {
+                    let target = automaton.transitions[state][&symbol];
+                    match grammar.nonterminal(symbol) {
+                        Some(n) => goto[state][n] = Some(target),
+                        None => {
+                            let t = grammar.terminal_index[&symbol];
+                            action[state][t] = resolve(action[state][t], Action::Shift(target));
+                        }
+                    }
+                }
+                None => {
+                    let candidate = if item.0 == 0 { Action::Accept } else { Action::Reduce(item.0) };
+                    for t in lookahead {
+                        action[state][t] = resolve(action[state][t], candidate);
+                    }
+                }
+            }
+        }
+    }
+
+    Tables {
+        terminals: grammar.terminals,
+        productions: grammar.productions,
+        action,
+        goto,
+        terminal_index: grammar.terminal_index,
+        nonterminal_index: grammar.nonterminal_index,
+    }
+}
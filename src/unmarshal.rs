@@ -1,61 +1,530 @@
+//! The document tree produced by the parser, and [`FromJson`] for mapping it
+//! into Rust types.
+
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::hash::BuildHasher;
+use std::ops::{Index, IndexMut};
+
+use crate::marshal::{self, MarshalOptions};
+use crate::number::Number;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum JsonValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Map),
+}
+
+static NULL: JsonValue = JsonValue::Null;
+
+impl JsonValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            JsonValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(Number::as_f64)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            JsonValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            JsonValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object().and_then(|o| o.get(key))
+    }
 
-pub type JsonValueType<'a> = &'a str;
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        self.as_object_mut().and_then(|o| o.get_mut(key))
+    }
 
-pub const STRING: JsonValueType = "STRING";
-pub const NUMBER: JsonValueType = "NUMBER";
-pub const BOOL: JsonValueType = "BOOLEAN";
-pub const NULL: JsonValueType = "NULL";
-pub const OBJECT: JsonValueType = "OBJECT";
-pub const ARRAY: JsonValueType = "ARRAY";
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "boolean",
+            JsonValue::Number(_) => "number",
+            JsonValue::String(_) => "string",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+        }
+    }
+}
 
-#[derive(Debug)]
-pub struct JsonValue<'a> {
-    value: Box<dyn std::any::Any>,
-    value_type: JsonValueType<'a>,
+/// Object members in insertion order.
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: Vec<(String, JsonValue)>,
+    // Position of each key in `entries`.
+    index: HashMap<String, usize>,
 }
 
-impl<'a> JsonValue<'a> {
-    pub fn new<T: 'static>(value: T, value_type: JsonValueType<'a>) -> Self {
-        JsonValue {
-            value: Box::new(value),
-            value_type,
+impl Map {
+    pub fn new() -> Self {
+        Map::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        self.index.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    pub fn get_key_value(&self, key: &str) -> Option<(&String, &JsonValue)> {
+        self.index.get(key).map(|&i| (&self.entries[i].0, &self.entries[i].1))
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a member, replacing the value of an existing key in place.
+    /// Returns the previous value if there was one.
+    pub fn insert(&mut self, key: String, value: JsonValue) -> Option<JsonValue> {
+        match self.get_mut(&key) {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        let pos = self.index.remove(key)?;
+        for i in self.index.values_mut() {
+            if *i > pos {
+                *i -= 1;
+            }
         }
+        Some(self.entries.remove(pos).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut JsonValue)> {
+        self.entries.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &JsonValue> {
+        self.entries.iter().map(|(_, v)| v)
     }
 
-    pub fn as_any(&self) -> &dyn std::any::Any {
-        &*self.value
+    /// Converts the member `key`. A missing member converts as `null`, so
+    /// optional fields may be left out.
+    pub fn field<T: FromJson>(&self, key: &str) -> Result<T, UnmarshalError> {
+        match self.get(key) {
+            Some(value) => T::from_json(value).map_err(|err| err.at(PathSegment::Key(key.to_string()))),
+            None => T::from_json(&NULL).map_err(|_| UnmarshalError::new(format!("missing field `{}`", key))),
+        }
     }
 }
 
-impl<'a> fmt::Display for JsonValue<'a> {
+impl fmt::Debug for Map {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.value_type {
-            "NULL" => write!(f, "null"),
-            "BOOLEAN" => {
-                if let Some(v) = self.as_any().downcast_ref::<bool>() {
-                    write!(f, "{}", v)
-                } else {
-                    write!(f, "Invalid bool value")
-                }
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Map {
+    /// Member order is not significant.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, JsonValue);
+    type IntoIter = std::vec::IntoIter<(String, JsonValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl FromIterator<(String, JsonValue)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, JsonValue)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    // Missing keys and non-objects index to null, so lookups can be chained.
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, i: usize) -> &JsonValue {
+        self.as_array().and_then(|a| a.get(i)).unwrap_or(&NULL)
+    }
+}
+
+impl IndexMut<&str> for JsonValue {
+    fn index_mut(&mut self, key: &str) -> &mut JsonValue {
+        let type_name = self.type_name();
+        match self.get_mut(key) {
+            Some(v) => v,
+            None => panic!("no key {:?} in {}", key, type_name),
+        }
+    }
+}
+
+impl IndexMut<usize> for JsonValue {
+    fn index_mut(&mut self, i: usize) -> &mut JsonValue {
+        let type_name = self.type_name();
+        match self.as_array_mut().and_then(|a| a.get_mut(i)) {
+            Some(v) => v,
+            None => panic!("no index {} in {}", i, type_name),
+        }
+    }
+}
+
+impl From<Number> for JsonValue {
+    fn from(n: Number) -> Self {
+        JsonValue::Number(n)
+    }
+}
+
+impl fmt::Display for JsonValue {
+    /// Writes compact JSON, or pretty-printed JSON with `{:#}`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = if f.alternate() {
+            MarshalOptions {
+                indent: Some("  ".to_string()),
+                ..MarshalOptions::default()
             }
-            "NUMBER" => {
-                if let Some(v) = self.as_any().downcast_ref::<f64>() {
-                    write!(f, "{}", v)
-                } else {
-                    write!(f, "Invalid number value")
-                }
+        } else {
+            MarshalOptions::default()
+        };
+        marshal::write_fmt(f, self, &options)
+    }
+}
+
+/// A step from a value to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Why a [`JsonValue`] could not be converted, and where in the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmarshalError {
+    path: Vec<PathSegment>,
+    message: String,
+}
+
+impl UnmarshalError {
+    pub fn new(message: impl Into<String>) -> Self {
+        UnmarshalError { path: Vec::new(), message: message.into() }
+    }
+
+    pub fn invalid_type(expected: &str, found: &JsonValue) -> Self {
+        let found = match found {
+            JsonValue::Null | JsonValue::Bool(_) | JsonValue::Number(_) => found.to_string(),
+            JsonValue::String(s) => format!("string {}", JsonValue::String(s.clone())),
+            _ => found.type_name().to_string(),
+        };
+        UnmarshalError::new(format!("expected {}, found {}", expected, found))
+    }
+
+    /// Places the error inside `segment`; containers call this as the error
+    /// passes up through them.
+    pub fn at(mut self, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// The segments from the root to the value that failed.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for UnmarshalError {
+    /// Writes the path as `$.key[0]`, quoting keys that are not identifiers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at $")?;
+        for segment in &self.path {
+            match segment {
+                PathSegment::Index(i) => write!(f, "[{}]", i)?,
+                PathSegment::Key(k) if is_identifier(k) => write!(f, ".{}", k)?,
+                PathSegment::Key(k) => write!(f, "[{}]", JsonValue::String(k.clone()))?,
             }
-            "STRING" => {
-                if let Some(v) = self.as_any().downcast_ref::<String>() {
-                    write!(f, "{}", v)
-                } else {
-                    write!(f, "Invalid string value")
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for UnmarshalError {}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Conversion from a parsed document into a Rust type.
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError>;
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromJson for Number {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        value.as_number().cloned().ok_or_else(|| UnmarshalError::invalid_type("a number", value))
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        value.as_bool().ok_or_else(|| UnmarshalError::invalid_type("a boolean", value))
+    }
+}
+
+impl FromJson for () {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        match value {
+            JsonValue::Null => Ok(()),
+            _ => Err(UnmarshalError::invalid_type("null", value)),
+        }
+    }
+}
+
+macro_rules! from_json_int {
+    ($($t:ty => $as:ident,)*) => {
+        $(
+            impl FromJson for $t {
+                fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+                    value
+                        .$as()
+                        .and_then(|n| <$t>::try_from(n).ok())
+                        .ok_or_else(|| UnmarshalError::invalid_type(stringify!($t), value))
                 }
             }
-            "ARRAY" => write!(f, "Array"), // Further implementation needed for arrays
-            "OBJECT" => write!(f, "Object"), // Further implementation needed for objects
-            _ => write!(f, "Unknown type"),
+        )*
+    };
+}
+
+from_json_int! {
+    i8 => as_i64, i16 => as_i64, i32 => as_i64, i64 => as_i64, isize => as_i64,
+    u8 => as_u64, u16 => as_u64, u32 => as_u64, u64 => as_u64, usize => as_u64,
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        value.as_f64().ok_or_else(|| UnmarshalError::invalid_type("a number", value))
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        f64::from_json(value).map(|f| f as f32)
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        value.as_str().map(str::to_string).ok_or_else(|| UnmarshalError::invalid_type("a string", value))
+    }
+}
+
+impl FromJson for char {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        let mut chars = value.as_str().unwrap_or_default().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(UnmarshalError::invalid_type("a single character", value)),
+        }
+    }
+}
+
+/// `null` converts to `None`.
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        match value {
+            JsonValue::Null => Ok(None),
+            _ => T::from_json(value).map(Some),
         }
     }
 }
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        T::from_json(value).map(Box::new)
+    }
+}
+
+fn elements<'a>(value: &'a JsonValue, expected: &str) -> Result<&'a [JsonValue], UnmarshalError> {
+    value.as_array().map(Vec::as_slice).ok_or_else(|| UnmarshalError::invalid_type(expected, value))
+}
+
+fn element<T: FromJson>(elements: &[JsonValue], i: usize) -> Result<T, UnmarshalError> {
+    T::from_json(&elements[i]).map_err(|err| err.at(PathSegment::Index(i)))
+}
+
+fn fixed_length(elements: &[JsonValue], len: usize) -> Result<(), UnmarshalError> {
+    if elements.len() == len {
+        Ok(())
+    } else {
+        Err(UnmarshalError::new(format!("expected {} elements, found {}", len, elements.len())))
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        let elements = elements(value, "an array")?;
+        (0..elements.len()).map(|i| element(elements, i)).collect()
+    }
+}
+
+impl<T: FromJson, const N: usize> FromJson for [T; N] {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        let elements = elements(value, "an array")?;
+        fixed_length(elements, N)?;
+        let converted = (0..N).map(|i| element(elements, i)).collect::<Result<Vec<T>, _>>()?;
+        Ok(converted.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+macro_rules! from_json_tuple {
+    ($($len:literal => ($($t:ident $i:tt),+),)*) => {
+        $(
+            impl<$($t: FromJson),+> FromJson for ($($t,)+) {
+                fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+                    let elements = elements(value, "an array")?;
+                    fixed_length(elements, $len)?;
+                    Ok(($(element::<$t>(elements, $i)?,)+))
+                }
+            }
+        )*
+    };
+}
+
+from_json_tuple! {
+    1 => (A 0),
+    2 => (A 0, B 1),
+    3 => (A 0, B 1, C 2),
+    4 => (A 0, B 1, C 2, D 3),
+    5 => (A 0, B 1, C 2, D 3, E 4),
+    6 => (A 0, B 1, C 2, D 3, E 4, F 5),
+    7 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6),
+    8 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7),
+}
+
+fn members<T: FromJson, C: FromIterator<(String, T)>>(value: &JsonValue) -> Result<C, UnmarshalError> {
+    let object = value.as_object().ok_or_else(|| UnmarshalError::invalid_type("an object", value))?;
+    object
+        .iter()
+        .map(|(k, v)| match T::from_json(v) {
+            Ok(v) => Ok((k.clone(), v)),
+            Err(err) => Err(err.at(PathSegment::Key(k.clone()))),
+        })
+        .collect()
+}
+
+impl<T: FromJson, S: BuildHasher + Default> FromJson for HashMap<String, T, S> {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        members(value)
+    }
+}
+
+impl<T: FromJson> FromJson for BTreeMap<String, T> {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        members(value)
+    }
+}
@@ -0,0 +1,966 @@
+//! JSON Schema validation of [`JsonValue`]s. A schema written for draft
+//! 2020-12 or draft-07 is compiled once into a [`Validator`], which can then
+//! check any number of instances.
+//!
+//! `$ref`s to the same document are resolved by JSON pointer, `$id` and
+//! `$anchor`; other documents are fetched through a [`Resolver`].
+//! `$dynamicRef` is treated as a plain `$ref`, and `format` is an
+//! annotation only.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::BuildHasher;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+use crate::iregexp::Regex;
+use crate::number::Number;
+use crate::pointer::escape;
+use crate::unmarshal::{JsonValue, Map};
+
+/// The dialect a schema is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Draft {
+    /// Draft-07: `definitions`, array-form `items` with `additionalItems`,
+    /// `dependencies`, and `$ref` overriding its sibling keywords.
+    V7,
+    #[default]
+    V2020_12,
+}
+
+/// Supplies the schemas that `$ref`s name by URI and that are not part of
+/// the schema being compiled.
+pub trait Resolver {
+    /// `uri` is absolute (as far as the referring schema's base allows) and
+    /// has no fragment.
+    fn resolve(&self, uri: &str) -> Option<JsonValue>;
+}
+
+impl<S: BuildHasher> Resolver for HashMap<String, JsonValue, S> {
+    fn resolve(&self, uri: &str) -> Option<JsonValue> {
+        self.get(uri).cloned()
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct ValidatorOptions<'a> {
+    /// The dialect of schemas whose `$schema` does not name one.
+    pub draft: Draft,
+    /// Without a resolver, `$ref`s to other documents fail to compile.
+    pub resolver: Option<&'a dyn Resolver>,
+}
+
+/// A schema that cannot be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// The offending keyword, as a URI of the schema document with a JSON
+    /// pointer fragment.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid schema at {}: {}", self.location, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// An instance that does not satisfy a keyword of the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// JSON pointer to the offending value in the instance.
+    pub instance_path: String,
+    /// JSON pointer to the keyword in the schema, through any `$ref`s
+    /// followed to reach it.
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = if self.instance_path.is_empty() { "(root)" } else { &self.instance_path };
+        write!(f, "{}: {}", at, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A compiled schema.
+pub struct Validator {
+    nodes: Vec<Node>,
+    root: usize,
+}
+
+impl Validator {
+    pub fn new(schema: &JsonValue) -> Result<Validator, SchemaError> {
+        Self::with_options(schema, &ValidatorOptions::default())
+    }
+
+    pub fn with_options(schema: &JsonValue, options: &ValidatorOptions) -> Result<Validator, SchemaError> {
+        let mut compiler = Compiler {
+            options: *options,
+            documents: Vec::new(),
+            resources: HashMap::new(),
+            anchors: HashMap::new(),
+            bases: HashMap::new(),
+            compiled: HashMap::new(),
+            nodes: Vec::new(),
+        };
+        let doc = compiler.add_document(String::new(), schema.clone());
+        let root = compiler.compile(doc, "")?;
+        Ok(Validator { nodes: compiler.nodes, root })
+    }
+
+    /// Checks `instance` against every keyword, returning all the failures.
+    pub fn validate(&self, instance: &JsonValue) -> Result<(), Vec<ValidationError>> {
+        let mut state = State { errors: Vec::new(), refs: Vec::new() };
+        self.check(self.root, instance, "", "", &mut state);
+        if state.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(state.errors)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonValue) -> bool {
+        self.validate(instance).is_ok()
+    }
+}
+
+enum Node {
+    Bool(bool),
+    Rules(Vec<Rule>),
+}
+
+struct Rule {
+    keyword: &'static str,
+    check: Check,
+}
+
+impl Rule {
+    fn new(keyword: &'static str, check: Check) -> Self {
+        Rule { keyword, check }
+    }
+}
+
+enum Check {
+    Ref(usize),
+    Type(Vec<String>),
+    Enum(Vec<JsonValue>),
+    Const(JsonValue),
+    MultipleOf(Number),
+    Bound { bound: Bound, limit: Number },
+    Size { of: Size, max: bool, limit: usize },
+    Pattern { source: String, regex: Regex },
+    PrefixItems(Vec<usize>),
+    // Applies to the items after the first `prefix`.
+    Items { prefix: usize, schema: usize },
+    Contains { schema: usize, min: usize, max: Option<usize> },
+    UniqueItems,
+    Properties(Vec<(String, usize)>),
+    PatternProperties(Vec<(String, Regex, usize)>),
+    // `patterns` is the index of the `patternProperties` rule beside it.
+    AdditionalProperties { schema: usize, properties: Vec<String>, patterns: Option<usize> },
+    PropertyNames(usize),
+    Required(Vec<String>),
+    DependentRequired(Vec<(String, Vec<String>)>),
+    DependentSchemas(Vec<(String, usize)>),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    If { condition: usize, then: Option<usize>, otherwise: Option<usize> },
+    UnevaluatedItems(usize),
+    UnevaluatedProperties(usize),
+}
+
+#[derive(Clone, Copy)]
+enum Bound {
+    Maximum,
+    ExclusiveMaximum,
+    Minimum,
+    ExclusiveMinimum,
+}
+
+#[derive(Clone, Copy)]
+enum Size {
+    Length,
+    Items,
+    Properties,
+}
+
+const BOUNDS: [(&str, Bound); 4] = [
+    ("maximum", Bound::Maximum),
+    ("exclusiveMaximum", Bound::ExclusiveMaximum),
+    ("minimum", Bound::Minimum),
+    ("exclusiveMinimum", Bound::ExclusiveMinimum),
+];
+
+const SIZES: [(&str, Size, bool); 6] = [
+    ("maxLength", Size::Length, true),
+    ("minLength", Size::Length, false),
+    ("maxItems", Size::Items, true),
+    ("minItems", Size::Items, false),
+    ("maxProperties", Size::Properties, true),
+    ("minProperties", Size::Properties, false),
+];
+
+const TYPES: [&str; 7] = ["array", "boolean", "integer", "null", "number", "object", "string"];
+
+// Keywords whose values are subschemas, by shape, for finding `$id`s and
+// `$anchor`s.
+const SCHEMA_KEYWORDS: [&str; 11] = [
+    "additionalItems",
+    "additionalProperties",
+    "contains",
+    "else",
+    "if",
+    "items",
+    "not",
+    "propertyNames",
+    "then",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+const LIST_KEYWORDS: [&str; 5] = ["allOf", "anyOf", "items", "oneOf", "prefixItems"];
+const MAP_KEYWORDS: [&str; 6] = ["$defs", "definitions", "dependencies", "dependentSchemas", "patternProperties", "properties"];
+
+struct Document {
+    uri: String,
+    root: Rc<JsonValue>,
+    draft: Draft,
+}
+
+// A location in a schema document: its index and a JSON pointer.
+type Location = (usize, String);
+
+struct Compiler<'a> {
+    options: ValidatorOptions<'a>,
+    documents: Vec<Document>,
+    // Absolute URIs of schema resources, and `uri#name` of anchors.
+    resources: HashMap<String, Location>,
+    anchors: HashMap<String, Location>,
+    // The base URI in effect at each subschema.
+    bases: HashMap<Location, String>,
+    compiled: HashMap<Location, usize>,
+    nodes: Vec<Node>,
+}
+
+impl Compiler<'_> {
+    fn add_document(&mut self, uri: String, root: JsonValue) -> usize {
+        let draft = match root.get("$schema").and_then(JsonValue::as_str) {
+            Some(s) if ["draft-04", "draft-06", "draft-07"].iter().any(|d| s.contains(d)) => Draft::V7,
+            Some(s) if s.contains("/draft/2019-09/") || s.contains("/draft/2020-12/") => Draft::V2020_12,
+            _ => self.options.draft,
+        };
+        let doc = self.documents.len();
+        let root = Rc::new(root);
+        self.resources.insert(uri.clone(), (doc, String::new()));
+        self.documents.push(Document { uri: uri.clone(), root: Rc::clone(&root), draft });
+        self.register(doc, &root, String::new(), &uri, draft);
+        doc
+    }
+
+    // Records the base URI of every subschema, and where each `$id` and
+    // `$anchor` is.
+    fn register(&mut self, doc: usize, value: &JsonValue, pointer: String, base: &str, draft: Draft) {
+        let mut base = base.to_string();
+        if let JsonValue::Object(schema) = value {
+            if let Some(id) = schema.get("$id").and_then(JsonValue::as_str) {
+                if draft == Draft::V7 && id.starts_with('#') {
+                    self.anchors.insert(format!("{}{}", base, id), (doc, pointer.clone()));
+                } else {
+                    base = strip_fragment(&join(&base, id)).to_string();
+                    self.resources.insert(base.clone(), (doc, pointer.clone()));
+                }
+            }
+            for keyword in ["$anchor", "$dynamicAnchor"] {
+                if let Some(anchor) = schema.get(keyword).and_then(JsonValue::as_str) {
+                    self.anchors.insert(format!("{}#{}", base, anchor), (doc, pointer.clone()));
+                }
+            }
+            for (keyword, value) in schema.iter() {
+                let keyword = keyword.as_str();
+                match value {
+                    JsonValue::Array(list) if LIST_KEYWORDS.contains(&keyword) => {
+                        for (i, sub) in list.iter().enumerate() {
+                            self.register(doc, sub, format!("{}/{}/{}", pointer, keyword, i), &base, draft);
+                        }
+                    }
+                    JsonValue::Object(map) if MAP_KEYWORDS.contains(&keyword) => {
+                        for (key, sub) in map.iter() {
+                            self.register(doc, sub, format!("{}/{}/{}", pointer, keyword, escape(key)), &base, draft);
+                        }
+                    }
+                    _ if SCHEMA_KEYWORDS.contains(&keyword) => {
+                        self.register(doc, value, format!("{}/{}", pointer, keyword), &base, draft);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.bases.insert((doc, pointer), base);
+    }
+
+    fn error(&self, doc: usize, pointer: &str, message: String) -> SchemaError {
+        SchemaError { location: format!("{}#{}", self.documents[doc].uri, pointer), message }
+    }
+
+    fn base(&self, doc: usize, pointer: &str) -> String {
+        let mut pointer = pointer;
+        loop {
+            if let Some(base) = self.bases.get(&(doc, pointer.to_string())) {
+                return base.clone();
+            }
+            match pointer.rfind('/') {
+                Some(i) => pointer = &pointer[..i],
+                None => return self.documents[doc].uri.clone(),
+            }
+        }
+    }
+
+    fn resolve(&mut self, doc: usize, pointer: &str, reference: &str) -> Result<Location, SchemaError> {
+        let uri = join(&self.base(doc, pointer), reference);
+        let (absolute, fragment) = match uri.split_once('#') {
+            Some((absolute, fragment)) => (absolute.to_string(), percent_decode(fragment)),
+            None => (uri.clone(), String::new()),
+        };
+
+        if !self.resources.contains_key(&absolute) {
+            let document = self.options.resolver.and_then(|resolver| resolver.resolve(&absolute));
+            let document = document.ok_or_else(|| self.error(doc, pointer, format!("cannot resolve {:?}", uri)))?;
+            self.add_document(absolute.clone(), document);
+        }
+
+        let target = if fragment.is_empty() || fragment.starts_with('/') {
+            let (target, prefix) = &self.resources[&absolute];
+            (*target, format!("{}{}", prefix, fragment))
+        } else {
+            let anchor = self.anchors.get(&format!("{}#{}", absolute, fragment)).cloned();
+            anchor.ok_or_else(|| self.error(doc, pointer, format!("no anchor for {:?}", uri)))?
+        };
+        if self.documents[target.0].root.pointer(&target.1).is_err() {
+            return Err(self.error(doc, pointer, format!("{:?} does not exist", uri)));
+        }
+        Ok(target)
+    }
+
+    fn compile(&mut self, doc: usize, pointer: &str) -> Result<usize, SchemaError> {
+        let location = (doc, pointer.to_string());
+        if let Some(&index) = self.compiled.get(&location) {
+            return Ok(index);
+        }
+        // Reserve the slot first, so that a schema can refer to itself.
+        let index = self.nodes.len();
+        self.nodes.push(Node::Bool(true));
+        self.compiled.insert(location, index);
+
+        let root = Rc::clone(&self.documents[doc].root);
+        self.nodes[index] = match root.pointer(pointer) {
+            Ok(JsonValue::Bool(b)) => Node::Bool(*b),
+            Ok(JsonValue::Object(schema)) => Node::Rules(self.rules(doc, pointer, schema)?),
+            found => {
+                let found = found.map_or("nothing", JsonValue::type_name);
+                return Err(self.error(doc, pointer, format!("expected a schema, found {}", found)));
+            }
+        };
+        Ok(index)
+    }
+
+    fn subschema(&mut self, doc: usize, pointer: &str, keyword: &str) -> Result<usize, SchemaError> {
+        self.compile(doc, &format!("{}/{}", pointer, keyword))
+    }
+
+    fn subschema_list(&mut self, doc: usize, pointer: &str, keyword: &str, value: &JsonValue) -> Result<Vec<usize>, SchemaError> {
+        let list = value.as_array().filter(|list| !list.is_empty());
+        let list = list.ok_or_else(|| self.error(doc, pointer, format!("{} must be a non-empty array", keyword)))?;
+        (0..list.len()).map(|i| self.compile(doc, &format!("{}/{}/{}", pointer, keyword, i))).collect()
+    }
+
+    fn subschema_map(&mut self, doc: usize, pointer: &str, keyword: &str, value: &JsonValue) -> Result<Vec<(String, usize)>, SchemaError> {
+        let map = self.object(doc, pointer, keyword, value)?;
+        map.keys().map(|key| Ok((key.clone(), self.compile(doc, &format!("{}/{}/{}", pointer, keyword, escape(key)))?))).collect()
+    }
+
+    fn object<'v>(&self, doc: usize, pointer: &str, keyword: &str, value: &'v JsonValue) -> Result<&'v Map, SchemaError> {
+        value.as_object().ok_or_else(|| self.error(doc, pointer, format!("{} must be an object", keyword)))
+    }
+
+    fn number(&self, doc: usize, pointer: &str, keyword: &str, value: &JsonValue) -> Result<Number, SchemaError> {
+        value.as_number().cloned().ok_or_else(|| self.error(doc, pointer, format!("{} must be a number", keyword)))
+    }
+
+    fn count(&self, doc: usize, pointer: &str, keyword: &str, value: &JsonValue) -> Result<usize, SchemaError> {
+        let count = match value.as_number() {
+            Some(n) if is_integer(n) && n.as_f64() >= 0.0 => n.as_u64().map_or(usize::MAX, |n| n as usize),
+            _ => return Err(self.error(doc, pointer, format!("{} must be a non-negative integer", keyword))),
+        };
+        Ok(count)
+    }
+
+    fn strings(&self, doc: usize, pointer: &str, keyword: &str, value: &JsonValue) -> Result<Vec<String>, SchemaError> {
+        let strings = value.as_array().and_then(|list| list.iter().map(|s| s.as_str().map(str::to_string)).collect());
+        strings.ok_or_else(|| self.error(doc, pointer, format!("{} must be an array of strings", keyword)))
+    }
+
+    fn regex(&self, doc: usize, pointer: &str, source: &str) -> Result<Regex, SchemaError> {
+        Regex::extended(source).ok_or_else(|| self.error(doc, pointer, format!("unsupported regular expression {:?}", source)))
+    }
+
+    fn rules(&mut self, doc: usize, pointer: &str, schema: &Map) -> Result<Vec<Rule>, SchemaError> {
+        let draft = self.documents[doc].draft;
+        let mut rules = Vec::new();
+
+        for keyword in ["$ref", "$dynamicRef"] {
+            if let Some(value) = schema.get(keyword) {
+                let reference = value.as_str().ok_or_else(|| self.error(doc, pointer, format!("{} must be a string", keyword)))?;
+                let (target_doc, target) = self.resolve(doc, pointer, reference)?;
+                rules.push(Rule::new(keyword, Check::Ref(self.compile(target_doc, &target)?)));
+            }
+        }
+        if draft == Draft::V7 && schema.contains_key("$ref") {
+            return Ok(rules);
+        }
+
+        if let Some(value) = schema.get("type") {
+            let names = match value {
+                JsonValue::Array(names) => names.iter().collect(),
+                name => vec![name],
+            };
+            let types: Option<Vec<_>> = names.into_iter().map(|name| name.as_str().filter(|name| TYPES.contains(name)).map(str::to_string)).collect();
+            let types = types.ok_or_else(|| self.error(doc, pointer, "type must be a type name or an array of them".to_string()))?;
+            rules.push(Rule::new("type", Check::Type(types)));
+        }
+        if let Some(value) = schema.get("enum") {
+            let values = value.as_array().ok_or_else(|| self.error(doc, pointer, "enum must be an array".to_string()))?;
+            rules.push(Rule::new("enum", Check::Enum(values.clone())));
+        }
+        if let Some(value) = schema.get("const") {
+            rules.push(Rule::new("const", Check::Const(value.clone())));
+        }
+
+        if let Some(value) = schema.get("multipleOf") {
+            let divisor = self.number(doc, pointer, "multipleOf", value)?;
+            if divisor.as_f64() <= 0.0 {
+                return Err(self.error(doc, pointer, "multipleOf must be greater than 0".to_string()));
+            }
+            rules.push(Rule::new("multipleOf", Check::MultipleOf(divisor)));
+        }
+        for (keyword, bound) in BOUNDS {
+            if let Some(value) = schema.get(keyword) {
+                let limit = self.number(doc, pointer, keyword, value)?;
+                rules.push(Rule::new(keyword, Check::Bound { bound, limit }));
+            }
+        }
+        for (keyword, of, max) in SIZES {
+            if let Some(value) = schema.get(keyword) {
+                let limit = self.count(doc, pointer, keyword, value)?;
+                rules.push(Rule::new(keyword, Check::Size { of, max, limit }));
+            }
+        }
+        if let Some(value) = schema.get("pattern") {
+            let source = value.as_str().ok_or_else(|| self.error(doc, pointer, "pattern must be a string".to_string()))?;
+            let regex = self.regex(doc, pointer, source)?;
+            rules.push(Rule::new("pattern", Check::Pattern { source: source.to_string(), regex }));
+        }
+
+        // Draft-07 spells `prefixItems` and `items` as array-form `items`
+        // and `additionalItems`.
+        let (tuple, rest) = match (draft, schema.get("items")) {
+            (_, Some(JsonValue::Array(_))) => (Some("items"), "additionalItems"),
+            (Draft::V7, _) => (None, "items"),
+            (Draft::V2020_12, _) => (Some("prefixItems"), "items"),
+        };
+        let mut prefix = 0;
+        if let Some((tuple, value)) = tuple.and_then(|tuple| Some((tuple, schema.get(tuple)?))) {
+            let list = self.subschema_list(doc, pointer, tuple, value)?;
+            prefix = list.len();
+            rules.push(Rule::new(tuple, Check::PrefixItems(list)));
+        }
+        if schema.contains_key(rest) {
+            let schema = self.subschema(doc, pointer, rest)?;
+            rules.push(Rule::new(rest, Check::Items { prefix, schema }));
+        }
+        if schema.contains_key("contains") {
+            let contains = self.subschema(doc, pointer, "contains")?;
+            let (mut min, mut max) = (1, None);
+            if draft == Draft::V2020_12 {
+                if let Some(value) = schema.get("minContains") {
+                    min = self.count(doc, pointer, "minContains", value)?;
+                }
+                if let Some(value) = schema.get("maxContains") {
+                    max = Some(self.count(doc, pointer, "maxContains", value)?);
+                }
+            }
+            rules.push(Rule::new("contains", Check::Contains { schema: contains, min, max }));
+        }
+        if schema.get("uniqueItems").and_then(JsonValue::as_bool) == Some(true) {
+            rules.push(Rule::new("uniqueItems", Check::UniqueItems));
+        }
+
+        let mut properties = Vec::new();
+        if let Some(value) = schema.get("properties") {
+            properties = self.subschema_map(doc, pointer, "properties", value)?;
+            rules.push(Rule::new("properties", Check::Properties(properties.clone())));
+        }
+        let mut patterns = None;
+        if let Some(value) = schema.get("patternProperties") {
+            let map = self.object(doc, pointer, "patternProperties", value)?;
+            let mut list = Vec::new();
+            for source in map.keys() {
+                let regex = self.regex(doc, pointer, source)?;
+                list.push((source.clone(), regex, self.compile(doc, &format!("{}/patternProperties/{}", pointer, escape(source)))?));
+            }
+            patterns = Some(rules.len());
+            rules.push(Rule::new("patternProperties", Check::PatternProperties(list)));
+        }
+        if schema.contains_key("additionalProperties") {
+            let additional = self.subschema(doc, pointer, "additionalProperties")?;
+            let properties = properties.into_iter().map(|(name, _)| name).collect();
+            rules.push(Rule::new("additionalProperties", Check::AdditionalProperties { schema: additional, properties, patterns }));
+        }
+        if schema.contains_key("propertyNames") {
+            rules.push(Rule::new("propertyNames", Check::PropertyNames(self.subschema(doc, pointer, "propertyNames")?)));
+        }
+        if let Some(value) = schema.get("required") {
+            rules.push(Rule::new("required", Check::Required(self.strings(doc, pointer, "required", value)?)));
+        }
+        if let Some(value) = schema.get("dependentRequired") {
+            let map = self.object(doc, pointer, "dependentRequired", value)?;
+            let list = map.iter().map(|(name, required)| Ok((name.clone(), self.strings(doc, pointer, "dependentRequired", required)?)));
+            rules.push(Rule::new("dependentRequired", Check::DependentRequired(list.collect::<Result<_, _>>()?)));
+        }
+        if let Some(value) = schema.get("dependentSchemas") {
+            rules.push(Rule::new("dependentSchemas", Check::DependentSchemas(self.subschema_map(doc, pointer, "dependentSchemas", value)?)));
+        }
+        if let Some(value) = schema.get("dependencies").filter(|_| draft == Draft::V7) {
+            // Each member is either a list of required names or a schema.
+            let map = self.object(doc, pointer, "dependencies", value)?;
+            let (mut required, mut schemas) = (Vec::new(), Vec::new());
+            for (name, dependency) in map.iter() {
+                match dependency {
+                    JsonValue::Array(_) => required.push((name.clone(), self.strings(doc, pointer, "dependencies", dependency)?)),
+                    _ => schemas.push((name.clone(), self.compile(doc, &format!("{}/dependencies/{}", pointer, escape(name)))?)),
+                }
+            }
+            rules.push(Rule::new("dependencies", Check::DependentRequired(required)));
+            rules.push(Rule::new("dependencies", Check::DependentSchemas(schemas)));
+        }
+
+        if let Some(value) = schema.get("allOf") {
+            rules.push(Rule::new("allOf", Check::AllOf(self.subschema_list(doc, pointer, "allOf", value)?)));
+        }
+        if let Some(value) = schema.get("anyOf") {
+            rules.push(Rule::new("anyOf", Check::AnyOf(self.subschema_list(doc, pointer, "anyOf", value)?)));
+        }
+        if let Some(value) = schema.get("oneOf") {
+            rules.push(Rule::new("oneOf", Check::OneOf(self.subschema_list(doc, pointer, "oneOf", value)?)));
+        }
+        if schema.contains_key("not") {
+            rules.push(Rule::new("not", Check::Not(self.subschema(doc, pointer, "not")?)));
+        }
+        if schema.contains_key("if") {
+            let condition = self.subschema(doc, pointer, "if")?;
+            let then = schema.contains_key("then").then(|| self.subschema(doc, pointer, "then")).transpose()?;
+            let otherwise = schema.contains_key("else").then(|| self.subschema(doc, pointer, "else")).transpose()?;
+            rules.push(Rule::new("if", Check::If { condition, then, otherwise }));
+        }
+
+        // These depend on what the keywords above evaluated, so come last.
+        if draft == Draft::V2020_12 {
+            if schema.contains_key("unevaluatedItems") {
+                rules.push(Rule::new("unevaluatedItems", Check::UnevaluatedItems(self.subschema(doc, pointer, "unevaluatedItems")?)));
+            }
+            if schema.contains_key("unevaluatedProperties") {
+                let unevaluated = self.subschema(doc, pointer, "unevaluatedProperties")?;
+                rules.push(Rule::new("unevaluatedProperties", Check::UnevaluatedProperties(unevaluated)));
+            }
+        }
+        Ok(rules)
+    }
+}
+
+struct State {
+    errors: Vec<ValidationError>,
+    // The `$ref`s being followed and the instances they apply to, so that
+    // a schema that refers to itself without descending into the instance
+    // does not loop.
+    refs: Vec<(usize, *const JsonValue)>,
+}
+
+impl State {
+    fn fail(&mut self, instance_path: &str, schema_path: &str, message: String) {
+        self.errors.push(ValidationError {
+            instance_path: instance_path.to_string(),
+            schema_path: schema_path.to_string(),
+            message,
+        });
+    }
+}
+
+// The members and items of an instance that keywords have evaluated, for
+// `unevaluatedProperties` and `unevaluatedItems`.
+#[derive(Default)]
+struct Evaluated {
+    properties: HashSet<String>,
+    all_properties: bool,
+    items: HashSet<usize>,
+    all_items: bool,
+}
+
+impl Evaluated {
+    fn merge(&mut self, other: Evaluated) {
+        self.properties.extend(other.properties);
+        self.all_properties |= other.all_properties;
+        self.items.extend(other.items);
+        self.all_items |= other.all_items;
+    }
+}
+
+impl Validator {
+    fn check(&self, node: usize, instance: &JsonValue, instance_path: &str, schema_path: &str, state: &mut State) -> Evaluated {
+        let mut evaluated = Evaluated::default();
+        let rules = match &self.nodes[node] {
+            Node::Bool(true) => return evaluated,
+            Node::Bool(false) => {
+                state.fail(instance_path, schema_path, "no value is allowed here".to_string());
+                return evaluated;
+            }
+            Node::Rules(rules) => rules,
+        };
+
+        for rule in rules {
+            let path = format!("{}/{}", schema_path, rule.keyword);
+            let item_path = |i: usize| format!("{}/{}", instance_path, i);
+            let member_path = |key: &str| format!("{}/{}", instance_path, escape(key));
+
+            match (&rule.check, instance) {
+                (Check::Ref(target), _) => {
+                    let key = (*target, ptr::from_ref(instance));
+                    if !state.refs.contains(&key) {
+                        state.refs.push(key);
+                        evaluated.merge(self.check(*target, instance, instance_path, &path, state));
+                        state.refs.pop();
+                    }
+                }
+                (Check::Type(types), _) if !types.iter().any(|name| has_type(instance, name)) => {
+                    state.fail(instance_path, &path, format!("expected {}, found {}", types.join(" or "), instance.type_name()));
+                }
+                (Check::Enum(values), _) if !values.contains(instance) => {
+                    state.fail(instance_path, &path, format!("expected one of {}", JsonValue::Array(values.clone())));
+                }
+                (Check::Const(value), _) if instance != value => {
+                    state.fail(instance_path, &path, format!("expected {}", value));
+                }
+                (Check::MultipleOf(divisor), JsonValue::Number(n)) if !is_multiple(n, divisor) => {
+                    state.fail(instance_path, &path, format!("{} is not a multiple of {}", n, divisor));
+                }
+                (Check::Bound { bound, limit }, JsonValue::Number(n)) => {
+                    let (ok, message) = match bound {
+                        Bound::Maximum => (n <= limit, "greater than the maximum"),
+                        Bound::ExclusiveMaximum => (n < limit, "not less than the exclusive maximum"),
+                        Bound::Minimum => (n >= limit, "less than the minimum"),
+                        Bound::ExclusiveMinimum => (n > limit, "not greater than the exclusive minimum"),
+                    };
+                    if !ok {
+                        state.fail(instance_path, &path, format!("{} is {} of {}", n, message, limit));
+                    }
+                }
+                (Check::Size { of, max, limit }, _) => {
+                    let (size, unit) = match (of, instance) {
+                        (Size::Length, JsonValue::String(s)) => (s.chars().count(), "characters"),
+                        (Size::Items, JsonValue::Array(items)) => (items.len(), "items"),
+                        (Size::Properties, JsonValue::Object(members)) => (members.len(), "properties"),
+                        _ => continue,
+                    };
+                    if *max && size > *limit {
+                        state.fail(instance_path, &path, format!("has {} {}, more than the maximum of {}", size, unit, limit));
+                    } else if !*max && size < *limit {
+                        state.fail(instance_path, &path, format!("has {} {}, fewer than the minimum of {}", size, unit, limit));
+                    }
+                }
+                (Check::Pattern { source, regex }, JsonValue::String(s)) if !regex.is_found(s) => {
+                    state.fail(instance_path, &path, format!("{:?} does not match the pattern {:?}", s, source));
+                }
+                (Check::PrefixItems(schemas), JsonValue::Array(items)) => {
+                    for (i, (item, schema)) in items.iter().zip(schemas).enumerate() {
+                        self.check(*schema, item, &item_path(i), &format!("{}/{}", path, i), state);
+                        evaluated.items.insert(i);
+                    }
+                }
+                (Check::Items { prefix, schema }, JsonValue::Array(items)) => {
+                    for (i, item) in items.iter().enumerate().skip(*prefix) {
+                        self.check(*schema, item, &item_path(i), &path, state);
+                    }
+                    evaluated.all_items = true;
+                }
+                (Check::Contains { schema, min, max }, JsonValue::Array(items)) => {
+                    let matched: Vec<usize> = (0..items.len())
+                        .filter(|&i| self.attempt(*schema, &items[i], &item_path(i), &path, state).is_some())
+                        .collect();
+                    if matched.len() < *min {
+                        let message = match *min {
+                            1 => "no item matches the schema in contains".to_string(),
+                            min => format!("{} items match the schema in contains, fewer than the minimum of {}", matched.len(), min),
+                        };
+                        state.fail(instance_path, &path, message);
+                    }
+                    if let Some(max) = max.filter(|max| matched.len() > *max) {
+                        let message = format!("{} items match the schema in contains, more than the maximum of {}", matched.len(), max);
+                        state.fail(instance_path, &path, message);
+                    }
+                    evaluated.items.extend(matched);
+                }
+                (Check::UniqueItems, JsonValue::Array(items)) => {
+                    let duplicate = (1..items.len()).find_map(|j| (0..j).find(|&i| items[i] == items[j]).map(|i| (i, j)));
+                    if let Some((i, j)) = duplicate {
+                        state.fail(instance_path, &path, format!("items {} and {} are equal", i, j));
+                    }
+                }
+                (Check::Properties(schemas), JsonValue::Object(members)) => {
+                    for (name, schema) in schemas {
+                        if let Some(value) = members.get(name) {
+                            self.check(*schema, value, &member_path(name), &format!("{}/{}", path, escape(name)), state);
+                            evaluated.properties.insert(name.clone());
+                        }
+                    }
+                }
+                (Check::PatternProperties(patterns), JsonValue::Object(members)) => {
+                    for (key, value) in members.iter() {
+                        for (source, _, schema) in patterns.iter().filter(|(_, regex, _)| regex.is_found(key)) {
+                            self.check(*schema, value, &member_path(key), &format!("{}/{}", path, escape(source)), state);
+                            evaluated.properties.insert(key.clone());
+                        }
+                    }
+                }
+                (Check::AdditionalProperties { schema, properties, patterns }, JsonValue::Object(members)) => {
+                    let patterns = match patterns.map(|i| &rules[i].check) {
+                        Some(Check::PatternProperties(patterns)) => patterns.as_slice(),
+                        _ => &[],
+                    };
+                    for (key, value) in members.iter() {
+                        if !properties.contains(key) && !patterns.iter().any(|(_, regex, _)| regex.is_found(key)) {
+                            self.check(*schema, value, &member_path(key), &path, state);
+                        }
+                    }
+                    evaluated.all_properties = true;
+                }
+                (Check::PropertyNames(schema), JsonValue::Object(members)) => {
+                    for key in members.keys() {
+                        self.check(*schema, &JsonValue::String(key.clone()), &member_path(key), &path, state);
+                    }
+                }
+                (Check::Required(names), JsonValue::Object(members)) => {
+                    for name in names.iter().filter(|name| !members.contains_key(name)) {
+                        state.fail(instance_path, &path, format!("missing required property {:?}", name));
+                    }
+                }
+                (Check::DependentRequired(dependencies), JsonValue::Object(members)) => {
+                    for (name, required) in dependencies.iter().filter(|(name, _)| members.contains_key(name)) {
+                        for missing in required.iter().filter(|r| !members.contains_key(r)) {
+                            state.fail(instance_path, &path, format!("missing property {:?}, required when {:?} is present", missing, name));
+                        }
+                    }
+                }
+                (Check::DependentSchemas(dependencies), JsonValue::Object(members)) => {
+                    for (name, schema) in dependencies.iter().filter(|(name, _)| members.contains_key(name)) {
+                        evaluated.merge(self.check(*schema, instance, instance_path, &format!("{}/{}", path, escape(name)), state));
+                    }
+                }
+                (Check::AllOf(schemas), _) => {
+                    for (i, schema) in schemas.iter().enumerate() {
+                        evaluated.merge(self.check(*schema, instance, instance_path, &format!("{}/{}", path, i), state));
+                    }
+                }
+                (Check::AnyOf(schemas), _) => {
+                    let mut matched = false;
+                    for (i, schema) in schemas.iter().enumerate() {
+                        if let Some(found) = self.attempt(*schema, instance, instance_path, &format!("{}/{}", path, i), state) {
+                            evaluated.merge(found);
+                            matched = true;
+                        }
+                    }
+                    if !matched {
+                        state.fail(instance_path, &path, "does not match any of the schemas in anyOf".to_string());
+                    }
+                }
+                (Check::OneOf(schemas), _) => {
+                    let mut matched = Vec::new();
+                    for (i, schema) in schemas.iter().enumerate() {
+                        if let Some(found) = self.attempt(*schema, instance, instance_path, &format!("{}/{}", path, i), state) {
+                            matched.push((i, found));
+                        }
+                    }
+                    match matched.len() {
+                        0 => state.fail(instance_path, &path, "does not match any of the schemas in oneOf".to_string()),
+                        1 => evaluated.merge(matched.pop().unwrap().1),
+                        _ => {
+                            let (first, second) = (matched[0].0, matched[1].0);
+                            state.fail(instance_path, &path, format!("matches both schemas {} and {} in oneOf", first, second));
+                        }
+                    }
+                }
+                (Check::Not(schema), _) if self.attempt(*schema, instance, instance_path, &path, state).is_some() => {
+                    state.fail(instance_path, &path, "matches the schema in not".to_string());
+                }
+                (Check::If { condition, then, otherwise }, _) => {
+                    let branch = match self.attempt(*condition, instance, instance_path, &path, state) {
+                        Some(found) => {
+                            evaluated.merge(found);
+                            then.map(|then| (then, "then"))
+                        }
+                        None => otherwise.map(|otherwise| (otherwise, "else")),
+                    };
+                    if let Some((schema, keyword)) = branch {
+                        evaluated.merge(self.check(schema, instance, instance_path, &format!("{}/{}", schema_path, keyword), state));
+                    }
+                }
+                (Check::UnevaluatedItems(schema), JsonValue::Array(items)) if !evaluated.all_items => {
+                    for (i, item) in items.iter().enumerate().filter(|(i, _)| !evaluated.items.contains(i)) {
+                        self.check(*schema, item, &item_path(i), &path, state);
+                    }
+                    evaluated.all_items = true;
+                }
+                (Check::UnevaluatedProperties(schema), JsonValue::Object(members)) if !evaluated.all_properties => {
+                    for (key, value) in members.iter().filter(|(key, _)| !evaluated.properties.contains(*key)) {
+                        self.check(*schema, value, &member_path(key), &path, state);
+                    }
+                    evaluated.all_properties = true;
+                }
+                // The keyword does not apply to this type of instance.
+                _ => {}
+            }
+        }
+        evaluated
+    }
+
+    // Checks `instance` without reporting errors, returning what was
+    // evaluated if it is valid.
+    fn attempt(&self, node: usize, instance: &JsonValue, instance_path: &str, schema_path: &str, state: &mut State) -> Option<Evaluated> {
+        let errors = mem::take(&mut state.errors);
+        let evaluated = self.check(node, instance, instance_path, schema_path, state);
+        let valid = state.errors.is_empty();
+        state.errors = errors;
+        valid.then_some(evaluated)
+    }
+}
+
+fn has_type(instance: &JsonValue, name: &str) -> bool {
+    match (name, instance) {
+        ("integer", JsonValue::Number(n)) => is_integer(n),
+        (name, instance) => name == instance.type_name(),
+    }
+}
+
+// Whether `n` has no fractional part, including floats such as `1.0`.
+fn is_integer(n: &Number) -> bool {
+    n.is_u64() || n.is_i64() || n.as_f64().fract() == 0.0
+}
+
+fn is_multiple(n: &Number, divisor: &Number) -> bool {
+    let exact = |n: &Number| n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from));
+    if let (Some(n), Some(divisor)) = (exact(n), exact(divisor)) {
+        return n % divisor == 0;
+    }
+    // Allow for the rounding of decimal fractions such as 0.01.
+    let quotient = n.as_f64() / divisor.as_f64();
+    quotient.is_finite() && (quotient - quotient.round()).abs() <= quotient.abs().max(1.0) * f64::EPSILON * 4.0
+}
+
+fn strip_fragment(uri: &str) -> &str {
+    uri.split('#').next().unwrap_or_default()
+}
+
+// The length of the `scheme:` prefix of `uri`, or 0 if it has none.
+fn scheme_len(uri: &str) -> usize {
+    match uri.find(':') {
+        Some(i) if uri.starts_with(|c: char| c.is_ascii_alphabetic())
+            && uri[..i].chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) =>
+        {
+            i + 1
+        }
+        _ => 0,
+    }
+}
+
+// Splits `uri` after its scheme and authority.
+fn split_authority(uri: &str) -> (&str, &str) {
+    let scheme = scheme_len(uri);
+    let authority = match uri[scheme..].strip_prefix("//") {
+        Some(rest) => 2 + rest.find(['/', '?', '#']).unwrap_or(rest.len()),
+        None => 0,
+    };
+    uri.split_at(scheme + authority)
+}
+
+// Resolves `reference` against `base` as in RFC 3986 section 5.2.
+fn join(base: &str, reference: &str) -> String {
+    let base = strip_fragment(base);
+    if reference.is_empty() || reference.starts_with('#') {
+        return format!("{}{}", base, reference);
+    }
+    let (prefix, path) = if scheme_len(reference) > 0 {
+        split_authority(reference)
+    } else if reference.starts_with("//") {
+        return format!("{}{}", &base[..scheme_len(base)], reference);
+    } else {
+        let (prefix, base_path) = split_authority(base);
+        if reference.starts_with('/') {
+            return format!("{}{}", prefix, remove_dot_segments(reference));
+        }
+        let base_path = base_path.split('?').next().unwrap_or_default();
+        let dir = match base_path.rfind('/') {
+            Some(i) => &base_path[..=i],
+            None if prefix.ends_with(':') || prefix.is_empty() => "",
+            None => "/",
+        };
+        return format!("{}{}", prefix, remove_dot_segments(&format!("{}{}", dir, reference)));
+    };
+    format!("{}{}", prefix, remove_dot_segments(path))
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let (path, rest) = path.split_at(path.find(['?', '#']).unwrap_or(path.len()));
+    let segments: Vec<&str> = path.split('/').collect();
+    let mut out: Vec<&str> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *segment {
+            "." => {}
+            ".." => {
+                if out.last().is_some_and(|s| !s.is_empty()) || out.len() > 1 {
+                    out.pop();
+                }
+            }
+            segment => {
+                out.push(segment);
+                continue;
+            }
+        }
+        if last {
+            out.push("");
+        }
+    }
+    format!("{}{}", out.join("/"), rest)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = s.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
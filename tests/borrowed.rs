@@ -0,0 +1,19 @@
+use std::borrow::Cow;
+
+use shift_reduce_json::{parse, parse_borrowed, BorrowedValue};
+
+#[test]
+fn only_escaped_strings_are_owned() {
+    let value = parse_borrowed(r#"{"plain": "text", "esc\naped": "tab\there"}"#).unwrap();
+    let members = value.as_object().unwrap();
+    assert!(matches!(members[0].0, Cow::Borrowed("plain")));
+    assert!(matches!(members[0].1, BorrowedValue::String(Cow::Borrowed("text"))));
+    assert!(matches!(members[1].0, Cow::Owned(_)));
+    assert_eq!(value.get("esc\naped").and_then(BorrowedValue::as_str), Some("tab\there"));
+}
+
+#[test]
+fn same_document_as_parse() {
+    let input = r#"[{"a": [1, -2.5, true, null], "b": {}}, "é", [], {"a": 1, "a": 2}]"#;
+    assert_eq!(parse_borrowed(input).unwrap().into_owned(), parse(input).unwrap());
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use shift_reduce_json::marshal::{to_string_with, write_io};
+use shift_reduce_json::{parse, to_string, to_string_pretty, FromJson, JsonValue, MarshalOptions, ToJson, UnmarshalError};
+
+#[derive(Debug, PartialEq)]
+struct Shape {
+    name: String,
+    sides: u8,
+    origin: (f64, f64),
+    tags: Option<Vec<String>>,
+}
+
+impl FromJson for Shape {
+    fn from_json(value: &JsonValue) -> Result<Self, UnmarshalError> {
+        let object = value.as_object().ok_or_else(|| UnmarshalError::invalid_type("an object", value))?;
+        Ok(Shape {
+            name: object.field("name")?,
+            sides: object.field("sides")?,
+            origin: object.field("origin")?,
+            tags: object.field("tags")?,
+        })
+    }
+}
+
+#[test]
+fn primitives_and_containers() {
+    let value = parse(r#"{"a": [1, 2, 3], "b": [-1, "x", true, null], "c": {"d": [[0.5]]}}"#).unwrap();
+    assert_eq!(<[u8; 3]>::from_json(&value["a"]).unwrap(), [1, 2, 3]);
+    assert_eq!(
+        <(i64, char, bool, Option<u8>)>::from_json(&value["b"]).unwrap(),
+        (-1, 'x', true, None)
+    );
+    let nested = BTreeMap::from([("d".to_string(), vec![vec![0.5f32]])]);
+    assert_eq!(BTreeMap::<String, Vec<Vec<f32>>>::from_json(&value["c"]).unwrap(), nested);
+
+    let shape = Shape::from_json(&parse(r#"{"name": "tri", "sides": 3, "origin": [0, 1.5]}"#).unwrap()).unwrap();
+    assert_eq!(shape, Shape { name: "tri".to_string(), sides: 3, origin: (0.0, 1.5), tags: None });
+}
+
+#[test]
+fn errors_carry_the_path() {
+    let value = parse(r#"{"shapes": [{"name": "sq", "sides": 4, "origin": [0, 0]}, {"name": "x", "sides": 300}]}"#).unwrap();
+    let err = HashMap::<String, Vec<Shape>>::from_json(&value).unwrap_err();
+    assert_eq!(err.to_string(), "at $.shapes[1].sides: expected u8, found 300");
+
+    let err = Shape::from_json(&parse(r#"{"name": "x", "sides": 3}"#).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "at $: missing field `origin`");
+
+    let err = HashMap::<String, (u8, u8)>::from_json(&parse(r#"{"a b": [1, "2"]}"#).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), r#"at $["a b"][1]: expected u8, found string "2""#);
+}
+
+#[test]
+fn to_json_round_trips() {
+    let map = HashMap::from([("k".to_string(), (1u8, Some("v"), [true, false]))]);
+    let value = map.to_json();
+    assert_eq!(value, parse(r#"{"k": [1, "v", [true, false]]}"#).unwrap());
+    assert_eq!(f64::NAN.to_json(), JsonValue::Null);
+    assert_eq!(HashMap::<String, (u8, Option<String>, [bool; 2])>::from_json(&value).unwrap()["k"].1.as_deref(), Some("v"));
+}
+
+// Accepts `room` bytes, then fails.
+struct Full {
+    room: usize,
+}
+
+impl Write for Full {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.room == 0 {
+            return Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+        }
+        let n = buf.len().min(self.room);
+        self.room -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn marshal_options() {
+    let value = parse(r#"{"b": [1, {"z": null, "a": []}], "a": {}, "é😀": "\u0001\b\f\n\"\\/"}"#).unwrap();
+    assert_eq!(to_string(&value), r#"{"b":[1,{"z":null,"a":[]}],"a":{},"é😀":"\u0001\b\f\n\"\\/"}"#);
+    assert_eq!(
+        to_string_pretty(&value),
+        "{\n  \"b\": [\n    1,\n    {\n      \"z\": null,\n      \"a\": []\n    }\n  ],\n  \"a\": {},\n  \"é😀\": \"\\u0001\\b\\f\\n\\\"\\\\/\"\n}\n"
+    );
+
+    let options = MarshalOptions { sort_keys: true, ascii_only: true, ..MarshalOptions::default() };
+    assert_eq!(to_string_with(&value, &options), r#"{"a":{},"b":[1,{"a":[],"z":null}],"\u00e9\ud83d\ude00":"\u0001\b\f\n\"\\/"}"#);
+    let options = MarshalOptions { indent: Some("\t".to_string()), trailing_newline: false, ..MarshalOptions::default() };
+    assert_eq!(to_string_with(&parse("[[1]]").unwrap(), &options), "[\n\t[\n\t\t1\n\t]\n]");
+
+    for input in [r#"{"k": [0, -1.5e-7, 12345678901234567890, "😀\u0000", true, null], "": {}}"#, "[]", r#""x""#] {
+        let value = parse(input).unwrap();
+        assert_eq!(parse(&to_string(&value)).unwrap(), value, "{}", input);
+        assert_eq!(parse(&to_string_pretty(&value)).unwrap(), value, "{}", input);
+        let ascii = to_string_with(&value, &MarshalOptions { ascii_only: true, ..MarshalOptions::default() });
+        assert!(ascii.is_ascii() && parse(&ascii).unwrap() == value, "{}", input);
+    }
+
+    let mut out = Vec::new();
+    write_io(&mut out, &value, &MarshalOptions::pretty()).unwrap();
+    assert_eq!(out, to_string_pretty(&value).into_bytes());
+    let error = write_io(Full { room: 10 }, &value, &MarshalOptions::default()).unwrap_err();
+    assert_eq!((error.kind(), error.to_string()), (io::ErrorKind::StorageFull, "disk full".to_string()));
+}
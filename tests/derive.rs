@@ -0,0 +1,124 @@
+#![cfg(feature = "derive")]
+
+use std::collections::HashMap;
+
+use shift_reduce_json::{parse, FromJson, JsonValue, ToJson};
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+#[json(deny_unknown_fields)]
+struct Server {
+    host: String,
+    #[json(rename = "listen-port")]
+    port: u16,
+    #[json(default)]
+    verbose: bool,
+    #[json(default = "default_workers")]
+    workers: usize,
+    #[json(skip)]
+    connections: u32,
+    limits: Limits,
+    shapes: Vec<Shape>,
+}
+
+fn default_workers() -> usize {
+    4
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+struct Limits {
+    timeout: Option<u32>,
+    #[json(flatten)]
+    rest: HashMap<String, u64>,
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+enum Shape {
+    Dot,
+    Circle(f64),
+    Line(i32, i32),
+    #[json(rename = "rect")]
+    Rect { w: u8, h: u8 },
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+#[json(tag = "type")]
+enum Event {
+    Start,
+    Move { x: i32 },
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+#[json(tag = "t", content = "c")]
+enum Message {
+    Ping,
+    Text(String),
+    Pair(u8, u8),
+}
+
+#[derive(Debug, PartialEq, FromJson, ToJson)]
+#[json(untagged)]
+enum Setting<T> {
+    Flag(bool),
+    Value(T),
+    Missing,
+}
+
+#[test]
+fn structs_round_trip() {
+    let input = r#"{"host": "a", "listen-port": 80, "limits": {"timeout": null, "rss": 10, "fds": 20},
+                    "shapes": ["Dot", {"Circle": 1.5}, {"Line": [1, -2]}, {"rect": {"w": 2, "h": 3}}]}"#;
+    let server = Server::from_json(&parse(input).unwrap()).unwrap();
+    assert_eq!(
+        server,
+        Server {
+            host: "a".to_string(),
+            port: 80,
+            verbose: false,
+            workers: 4,
+            connections: 0,
+            limits: Limits { timeout: None, rest: HashMap::from([("rss".to_string(), 10), ("fds".to_string(), 20)]) },
+            shapes: vec![Shape::Dot, Shape::Circle(1.5), Shape::Line(1, -2), Shape::Rect { w: 2, h: 3 }],
+        }
+    );
+
+    let value = server.to_json();
+    assert_eq!(value["listen-port"], parse("80").unwrap());
+    assert!(value.get("connections").is_none());
+    assert_eq!(value["limits"]["rss"], parse("10").unwrap());
+    assert_eq!(Server::from_json(&value).unwrap(), server);
+}
+
+#[test]
+fn enum_tagging() {
+    let events = vec![Event::Start, Event::Move { x: -1 }];
+    assert_eq!(events.to_json(), parse(r#"[{"type": "Start"}, {"type": "Move", "x": -1}]"#).unwrap());
+    assert_eq!(Vec::<Event>::from_json(&events.to_json()).unwrap(), events);
+
+    let messages = vec![Message::Ping, Message::Text("hi".to_string()), Message::Pair(1, 2)];
+    assert_eq!(
+        messages.to_json(),
+        parse(r#"[{"t": "Ping"}, {"t": "Text", "c": "hi"}, {"t": "Pair", "c": [1, 2]}]"#).unwrap()
+    );
+    assert_eq!(Vec::<Message>::from_json(&messages.to_json()).unwrap(), messages);
+
+    let settings = vec![Setting::Flag(true), Setting::Value("x".to_string()), Setting::Missing];
+    assert_eq!(settings.to_json(), parse(r#"[true, "x", null]"#).unwrap());
+    assert_eq!(Vec::<Setting<String>>::from_json(&settings.to_json()).unwrap(), settings);
+}
+
+#[test]
+fn errors() {
+    let error = |input: &str| Server::from_json(&parse(input).unwrap()).unwrap_err().to_string();
+    let base = r#""host": "a", "listen-port": 80, "limits": {}"#;
+    assert_eq!(error(&format!("{{{}, \"shapes\": [], \"extra\": 1}}", base)), "at $: unknown field `extra`");
+    assert_eq!(error(&format!("{{{}, \"shapes\": [{{\"Line\": [1]}}]}}", base)), "at $.shapes[0].Line: expected 2 elements, found 1");
+    assert_eq!(
+        error(&format!("{{{}, \"shapes\": [\"Square\"]}}", base)),
+        "at $.shapes[0]: unknown variant `Square`, expected one of `Dot`, `Circle`, `Line`, `rect`"
+    );
+    assert_eq!(
+        Event::from_json(&parse(r#"{"type": "Move", "x": "1"}"#).unwrap()).unwrap_err().to_string(),
+        r#"at $.x: expected i32, found string "1""#
+    );
+    assert!(Setting::<u8>::from_json(&JsonValue::String("x".to_string())).is_err());
+}
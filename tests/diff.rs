@@ -0,0 +1,95 @@
+use shift_reduce_json::diff::{diff_with, render, ArrayDiff, DiffOptions};
+use shift_reduce_json::{apply_patch, diff, parse, JsonValue};
+
+fn json(input: &str) -> JsonValue {
+    parse(input).unwrap()
+}
+
+const PAIRS: &[(&str, &str)] = &[
+    ("1", "1"),
+    ("1", r#""one""#),
+    (r#"{"a": 1, "b": [1, 2]}"#, r#"{"b": [1, 2, 3], "c": {"~/": null}}"#),
+    ("[1, 2, 3, 4, 5]", "[0, 2, 4, 5, 6, 7]"),
+    ("[1, 2, 3]", "[]"),
+    ("[]", "[[1], {}]"),
+    (r#"[{"a": [1, 2]}, 3]"#, r#"[{"a": [2]}, 3, 3]"#),
+    (r#"[{"id": 1, "v": "a"}, {"id": 2, "v": "b"}, {"id": 3}, 4]"#, r#"[{"id": 3, "x": 0}, 5, {"id": 1, "v": "c"}, {"id": 4}, 4]"#),
+    (r#"[{"id": 1}, {"id": 1}, "x"]"#, r#"["x", {"id": 1, "n": 2}]"#),
+    (r#"[{"id": 1.0}, {"id": -0}, [1e400]]"#, r#"[[10e399], {"id": 0, "n": 1}, {"id": 1}]"#),
+];
+
+#[test]
+fn patches_turn_a_into_b() {
+    let keyed = DiffOptions { arrays: ArrayDiff::Key("id".to_string()) };
+    for (a, b) in PAIRS {
+        for options in [&DiffOptions::default(), &keyed] {
+            let (a, b) = (json(a), json(b));
+            let patch = diff_with(&a, &b, options);
+            let mut patched = a.clone();
+            apply_patch(&mut patched, &patch).unwrap();
+            assert_eq!(patched, b, "{} with {:?}", patch, options);
+        }
+    }
+}
+
+#[test]
+fn patches_are_small() {
+    assert_eq!(diff(&json(r#"{"a": [1, 2]}"#), &json(r#"{"a": [1, 2]}"#)), json("[]"));
+    assert_eq!(
+        diff(&json(r#"{"a": {"b": 1, "c": 2}}"#), &json(r#"{"a": {"b": 1, "c": 3}}"#)),
+        json(r#"[{"op": "replace", "path": "/a/c", "value": 3}]"#)
+    );
+    assert_eq!(
+        diff(&json("[1, 2, 3]"), &json("[1, 3, 4]")),
+        json(r#"[{"op": "remove", "path": "/1"}, {"op": "add", "path": "/2", "value": 4}]"#)
+    );
+
+    let keyed = DiffOptions { arrays: ArrayDiff::Key("id".to_string()) };
+    assert_eq!(
+        diff_with(&json(r#"[{"id": "a"}, {"id": "b", "n": 1}]"#), &json(r#"[{"id": "b", "n": 2}, {"id": "a"}]"#), &keyed),
+        json(r#"[{"op": "move", "from": "/1", "path": "/0"}, {"op": "replace", "path": "/0/n", "value": 2}]"#)
+    );
+}
+
+#[test]
+fn rendering() {
+    let old = json(r#"{"a": 1, "b": [true], "c": null}"#);
+    let new = json(r#"{"a": 2, "b": [true, false], "d": "x"}"#);
+    assert_eq!(
+        render(&old, &diff(&old, &new), false),
+        "~ /a: 1 -> 2\n+ /b/1: false\n- /c: null\n+ /d: \"x\"\n"
+    );
+    assert_eq!(render(&old, &diff(&old, &json("[]")), true), "\x1b[33m~ (root): {\"a\":1,\"b\":[true],\"c\":null} -> []\x1b[0m\n");
+}
+
+#[test]
+fn long_arrays() {
+    let a: Vec<JsonValue> = (0..100_000u64).map(|n| JsonValue::Number(n.into())).collect();
+    let mut b = a.clone();
+    b[50_000] = JsonValue::Null;
+    b.insert(70_000, JsonValue::Bool(true));
+    b.remove(10);
+    let (a, b) = (JsonValue::Array(a), JsonValue::Array(b));
+    assert_eq!(
+        diff(&a, &b),
+        json(r#"[{"op": "remove", "path": "/10"}, {"op": "replace", "path": "/49999", "value": null}, {"op": "add", "path": "/69999", "value": true}]"#)
+    );
+
+    let c = JsonValue::Array((0..3000u64).map(|n| JsonValue::Number(n.into())).collect());
+    let d = JsonValue::Array((0..3000u64).rev().map(|n| JsonValue::Number(n.into())).collect());
+    assert_eq!(diff(&c, &d), JsonValue::Array(vec![json(&format!(r#"{{"op": "replace", "path": "", "value": {}}}"#, d))]));
+    let mut patched = c.clone();
+    apply_patch(&mut patched, &diff(&c, &d)).unwrap();
+    assert_eq!(patched, d);
+
+    let keyed = DiffOptions { arrays: ArrayDiff::Key("id".to_string()) };
+    let e: Vec<JsonValue> = (0..50_000u64)
+        .map(|n| JsonValue::Object([("id".to_string(), JsonValue::Number(n.into()))].into_iter().collect()))
+        .collect();
+    let mut f = e.clone();
+    f.rotate_right(1);
+    assert_eq!(
+        diff_with(&JsonValue::Array(e), &JsonValue::Array(f), &keyed),
+        json(r#"[{"op": "move", "from": "/49999", "path": "/0"}]"#)
+    );
+}
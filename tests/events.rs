@@ -0,0 +1,43 @@
+use shift_reduce_json::{parse_events, Event, JsonValue, Number};
+
+fn events(input: &str) -> (Vec<Event>, bool) {
+    let mut events = Vec::new();
+    let ok = parse_events(input, |event| events.push(event)).is_ok();
+    (events, ok)
+}
+
+#[test]
+fn events_in_document_order() {
+    let (events, ok) = events(r#"{"a": [1, {}], "b": "x"}"#);
+    assert!(ok);
+    assert_eq!(
+        events,
+        vec![
+            Event::StartObject,
+            Event::Key("a".to_string()),
+            Event::StartArray,
+            Event::Scalar(JsonValue::Number(Number::from(1))),
+            Event::StartObject,
+            Event::EndObject,
+            Event::EndArray,
+            Event::Key("b".to_string()),
+            Event::Scalar(JsonValue::String("x".to_string())),
+            Event::EndObject,
+        ]
+    );
+}
+
+#[test]
+fn events_before_an_error_are_reported() {
+    let (events, ok) = events("[null, [true");
+    assert!(!ok);
+    assert_eq!(
+        events,
+        vec![
+            Event::StartArray,
+            Event::Scalar(JsonValue::Null),
+            Event::StartArray,
+            Event::Scalar(JsonValue::Bool(true)),
+        ]
+    );
+}
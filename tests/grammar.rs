@@ -0,0 +1,29 @@
+use shift_reduce_json::check::{check_grammar, Conflict, ConflictKind};
+use shift_reduce_json::grammar::ElementType;
+
+#[test]
+fn grammar_has_no_conflicts() {
+    let report = check_grammar();
+    assert!(report.conflicts.is_empty(), "{}", report);
+}
+
+#[test]
+fn report_covers_every_non_terminal() {
+    let report = check_grammar();
+    for (n, first) in &report.first {
+        assert!(!first.is_empty(), "FIRST({}) is empty", n);
+    }
+    assert!(report.follow.iter().all(|(_, follow)| !follow.is_empty()));
+    assert!(!report.states.is_empty());
+}
+
+#[test]
+fn report_counts_conflicts() {
+    let mut report = check_grammar();
+    assert!(report.to_string().ends_with("\nno conflicts\n"));
+    let conflict = Conflict { state: 3, lookahead: ElementType::Comma, kind: ConflictKind::ShiftReduce, items: Vec::new() };
+    report.conflicts.push(conflict.clone());
+    assert!(report.to_string().ends_with("\n1 conflict\n"));
+    report.conflicts.push(conflict);
+    assert!(report.to_string().ends_with("\n2 conflicts\n"));
+}
@@ -0,0 +1,124 @@
+use shift_reduce_json::jsonpath::JsonPath;
+use shift_reduce_json::{parse, JsonValue};
+
+const STORE: &str = r#"{ "store": {
+    "book": [
+      { "category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95 },
+      { "category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99 },
+      { "category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+      { "category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+    ],
+    "bicycle": { "color": "red", "price": 399 }
+  }
+}"#;
+
+fn paths(doc: &JsonValue, query: &str) -> Vec<String> {
+    doc.query(query).unwrap().iter().map(|m| m.path.to_string()).collect()
+}
+
+#[test]
+fn rfc_9535_examples() {
+    let doc = parse(STORE).unwrap();
+    let authors: Vec<&str> = doc.query("$.store.book[*].author").unwrap().iter().map(|m| m.value.as_str().unwrap()).collect();
+    assert_eq!(authors, ["Nigel Rees", "Evelyn Waugh", "Herman Melville", "J. R. R. Tolkien"]);
+    assert_eq!(doc.query("$..author").unwrap().len(), 4);
+    assert_eq!(doc.query("$.store.*").unwrap().len(), 2);
+    assert_eq!(doc.query("$.store..price").unwrap().len(), 5);
+    assert_eq!(paths(&doc, "$..book[2]"), ["$['store']['book'][2]"]);
+    assert_eq!(paths(&doc, "$..book[-1]"), ["$['store']['book'][3]"]);
+    assert_eq!(paths(&doc, "$..book[:2]"), ["$['store']['book'][0]", "$['store']['book'][1]"]);
+    assert_eq!(paths(&doc, "$..book[0,1]"), paths(&doc, "$..book[:2]"));
+    assert_eq!(paths(&doc, "$..book[?@.isbn]"), ["$['store']['book'][2]", "$['store']['book'][3]"]);
+    assert_eq!(paths(&doc, "$..book[?@.price<10]"), ["$['store']['book'][0]", "$['store']['book'][2]"]);
+    assert_eq!(doc.query("$..*").unwrap().len(), 27);
+
+    let m = &doc.query("$.store.bicycle['color']").unwrap()[0];
+    assert_eq!(m.path.to_pointer(), "/store/bicycle/color");
+    assert_eq!(doc.pointer(&m.path.to_pointer()).unwrap(), m.value);
+}
+
+#[test]
+fn slices_filters_and_functions() {
+    let doc = parse(r#"["a", "b", "c", "d", "e", "f", "g"]"#).unwrap();
+    let values = |query: &str| -> Vec<String> {
+        doc.query(query).unwrap().iter().map(|m| m.value.as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(values("$[1:3]"), ["b", "c"]);
+    assert_eq!(values("$[5:]"), ["f", "g"]);
+    assert_eq!(values("$[1:5:2]"), ["b", "d"]);
+    assert_eq!(values("$[5:1:-2]"), ["f", "d"]);
+    assert_eq!(values("$[::-1]").concat(), "gfedcba");
+    assert!(values("$[::0]").is_empty());
+
+    let doc = parse(r#"{"a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}], "o": {"p": 1, "q": 2}}"#).unwrap();
+    assert_eq!(paths(&doc, "$.a[?@.b == 'kilo']"), ["$['a'][9]"]);
+    assert_eq!(paths(&doc, "$.a[?@ > 3.5]"), ["$['a'][1]", "$['a'][4]", "$['a'][5]"]);
+    assert_eq!(paths(&doc, "$.a[?@.b]").len(), 4);
+    assert_eq!(paths(&doc, "$.a[?!@.b]").len(), 6);
+    assert_eq!(paths(&doc, "$.a[?@ < 2 || @.b == 'k']"), ["$['a'][2]", "$['a'][7]"]);
+    assert_eq!(paths(&doc, "$.a[?match(@.b, '[jk]')]"), ["$['a'][6]", "$['a'][7]"]);
+    assert_eq!(paths(&doc, "$.a[?search(@.b, '[jk]')]"), ["$['a'][6]", "$['a'][7]", "$['a'][9]"]);
+    assert_eq!(paths(&doc, "$.a[?length(@.b) == 4]"), ["$['a'][9]"]);
+    assert_eq!(paths(&doc, "$[?count(@.*) == 2]"), ["$['o']"]);
+    assert_eq!(paths(&doc, "$[?value(@.p) == 1]"), ["$['o']"]);
+    assert_eq!(paths(&doc, "$.o[?@ == $.o.q]"), ["$['o']['q']"]);
+    assert_eq!(paths(&doc, "$.a[?@.b == @.missing]"), ["$['a'][0]", "$['a'][1]", "$['a'][2]", "$['a'][3]", "$['a'][4]", "$['a'][5]"]);
+}
+
+#[test]
+fn invalid_queries() {
+    for query in [
+        "", "store", "$.", "$[", "$[01]", "$[-0]", "$['a'", "$.a ", "$[?@.* == 1]", "$[?length(@.*) == 1]",
+        "$[?match(@.a, 'x') == true]", "$[?length(@.a)]", "$[?foo(@)]", "$[?1]", "$[9007199254740992]",
+    ] {
+        assert!(JsonPath::parse(query).is_err(), "{:?}", query);
+    }
+    let err = JsonPath::parse("$.a[?@.b = 1]").unwrap_err();
+    assert_eq!(err.offset, 9);
+    assert_eq!(err.to_string(), "invalid JSONPath at offset 9: expected `]`");
+}
+
+#[test]
+fn regex_functions_do_not_backtrack() {
+    let long = format!("{}b", "a".repeat(100_000));
+    let doc = JsonValue::Array(vec![JsonValue::String(long[..100_000].to_string()), JsonValue::String(long.clone())]);
+    assert_eq!(paths(&doc, "$[?match(@, 'a*')]"), ["$[0]"]);
+    assert_eq!(paths(&doc, "$[?match(@, '(a|a)*')]"), ["$[0]"]);
+    assert_eq!(paths(&doc, "$[?match(@, '(a*)*b')]"), ["$[1]"]);
+    assert_eq!(paths(&doc, "$[?search(@, '(a|aa)+b')]"), ["$[1]"]);
+    assert!(paths(&doc, "$[?match(@, '(a?){50}a{50}c')]").is_empty());
+
+    let doc = parse(r#"{"p": "x{2,3}", "items": ["xx", "xxxx", "x{2,3}"]}"#).unwrap();
+    assert_eq!(paths(&doc, "$.items[?match(@, $.p)]"), ["$['items'][0]"]);
+    assert_eq!(paths(&doc, "$.items[?match(@, 'x{2,3}')]"), ["$['items'][0]"]);
+    assert_eq!(paths(&doc, "$.items[?search(@, 'x{4}')]"), ["$['items'][1]"]);
+    assert!(paths(&doc, "$.items[?match(@, 'x(')]").is_empty());
+    assert!(paths(&doc, "$.items[?match(@, 'x{99999}{99999}')]").is_empty());
+}
+
+#[test]
+fn regex_categories() {
+    let doc = parse(
+        r#"["7", "½", "Ⅻ", "a", "\u01c5", "\u02b0", "中", "\u0301", "-", "«", "$", "+", "\u00a0", "\u2028",
+            "\u00ad", "\u0001", "\u0378", "\ue000"]"#,
+    )
+    .unwrap();
+    let matching = |category: &str| -> Vec<String> {
+        let query = format!(r"$[?match(@, '\\p{{{}}}')]", category);
+        doc.query(&query).unwrap().iter().map(|m| m.value.as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(matching("Nd"), ["7"]);
+    assert_eq!(matching("N"), ["7", "½", "Ⅻ"]);
+    assert_eq!(matching("L"), ["a", "\u{1c5}", "\u{2b0}", "中"]);
+    assert_eq!(matching("Lt"), ["\u{1c5}"]);
+    assert_eq!(matching("Lo"), ["中"]);
+    assert_eq!(matching("Mn"), ["\u{301}"]);
+    assert_eq!(matching("P"), ["-", "«"]);
+    assert_eq!(matching("Pi"), ["«"]);
+    assert_eq!(matching("S"), ["$", "+"]);
+    assert_eq!(matching("Z"), ["\u{a0}", "\u{2028}"]);
+    assert_eq!(matching("Zl"), ["\u{2028}"]);
+    assert_eq!(matching("C"), ["\u{ad}", "\u{1}", "\u{378}", "\u{e000}"]);
+    assert_eq!(matching("Cn"), ["\u{378}"]);
+    assert!(doc.query(r"$[?match(@, '\\p{Xx}')]").unwrap().is_empty());
+}
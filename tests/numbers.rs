@@ -0,0 +1,126 @@
+use shift_reduce_json::{diff, parse, parse_with, to_string, JsonValue, Number, ParseOptions};
+
+fn number(input: &str) -> f64 {
+    match parse(input) {
+        Ok(JsonValue::Number(n)) => n.as_f64(),
+        other => panic!("{:?} did not parse as a number: {:?}", input, other),
+    }
+}
+
+#[test]
+fn valid_numbers() {
+    let cases: &[(&str, f64)] = &[
+        ("0", 0.0),
+        ("-0", -0.0),
+        ("7", 7.0),
+        ("-7", -7.0),
+        ("1234567890", 1234567890.0),
+        ("0.5", 0.5),
+        ("-0.125", -0.125),
+        ("10.0", 10.0),
+        ("1e3", 1e3),
+        ("1E3", 1e3),
+        ("1e+3", 1e3),
+        ("1e-3", 1e-3),
+        ("0e0", 0.0),
+        ("-2.5E-2", -2.5e-2),
+        ("1e007", 1e7),
+    ];
+
+    for &(input, expected) in cases {
+        assert_eq!(number(input), expected, "{}", input);
+    }
+}
+
+#[test]
+fn negative_zero_keeps_its_sign() {
+    assert!(number("-0").is_sign_negative());
+    assert!(number("-0.0").is_sign_negative());
+}
+
+#[test]
+fn invalid_numbers() {
+    let cases = [
+        "+1", "01", "-01", "00", "1.", ".5", "-.5", "1.e5", "1e", "1e+", "1E-", "-", "--1", "- 1",
+        "1.2.3", "1e2e3", "0x1", "1_000", "Infinity", "NaN", "+", "e5", "1 2",
+    ];
+
+    for input in cases {
+        assert!(parse(input).is_err(), "{:?} should be rejected", input);
+    }
+}
+
+#[test]
+fn numbers_inside_containers() {
+    let value = parse("[0, -1.5e+2, {\"n\": 1E-1}]").unwrap();
+    assert_eq!(value[0].as_f64(), Some(0.0));
+    assert_eq!(value[1].as_f64(), Some(-150.0));
+    assert_eq!(value[2]["n"].as_f64(), Some(0.1));
+}
+
+#[test]
+fn invalid_numbers_inside_containers() {
+    for input in ["[01]", "[1.]", "{\"n\": +1}", "[-]", "[1e]"] {
+        assert!(parse(input).is_err(), "{:?} should be rejected", input);
+    }
+}
+
+#[test]
+fn literals_write_back_without_loss() {
+    let value = parse("[9007199254740993, 18446744073709551615, -9223372036854775808, 1e400, -1E+400, 0.1, 18446744073709551616]").unwrap();
+    assert_eq!(value[0].as_u64(), Some(9007199254740993));
+    assert_eq!(value[1].as_u64(), Some(u64::MAX));
+    assert_eq!(value[2].as_i64(), Some(i64::MIN));
+    assert_eq!(value[3].as_f64(), Some(f64::INFINITY));
+    assert_eq!(to_string(&value), "[9007199254740993,18446744073709551615,-9223372036854775808,1e400,-1E+400,0.1,1.8446744073709552e19]");
+
+    let raw = ParseOptions { raw_numbers: true, ..ParseOptions::default() };
+    let input = "[1.50,-0,1E+2,12345678901234567890123,0.1e-999]";
+    let value = parse_with(input, &raw).unwrap();
+    assert_eq!(to_string(&value), input);
+    assert_eq!(value[0].as_number().and_then(Number::as_raw), Some("1.50"));
+    assert_eq!(value[2].as_u64(), None);
+    assert_eq!(value[2].as_f64(), Some(100.0));
+    assert_eq!(Number::from_raw("2.0e0").unwrap().to_string(), "2.0e0");
+    assert!(Number::from_raw(" 2").is_none() && Number::from_raw("02").is_none());
+}
+
+#[test]
+fn numbers_compare_by_value() {
+    let n = |input: &str| match parse(input).unwrap() {
+        JsonValue::Number(n) => n,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(n("1"), n("1.0"));
+    assert_eq!(n("100"), n("1e2"));
+    assert_eq!(n("0"), n("-0"));
+    assert_eq!(Number::from_raw("1.00").unwrap(), Number::from(1u64));
+    assert_ne!(n("9007199254740993"), n("9007199254740992"));
+    assert!(n("9007199254740993") > n("9007199254740992.0"));
+    assert!(n("-9223372036854775808") < n("-9223372036854775807"));
+    assert!(n("18446744073709551615") < n("1e20"));
+    assert!(n("1.5") > n("1") && n("1.5") < n("2"));
+    assert!(n("1e400") > Number::from(u64::MAX));
+    assert!(n("-1e400") < n("-1e308"));
+    assert_eq!(n("2").partial_cmp(&n("2.5")), Some(std::cmp::Ordering::Less));
+
+    // Literals beyond f64, and raw ones, compare exactly.
+    assert_ne!(n("1e400"), n("2e400"));
+    assert!(n("1e400") < n("2e400") && n("-1e400") > n("-2e400"));
+    assert_eq!(n("1e400"), n("10e399"));
+    let raw = |input: &str| Number::from_raw(input).unwrap();
+    assert!(raw("1e-400") > n("0") && raw("-1e-400") < n("-0.0"));
+    assert_ne!(raw("12345678901234567890123"), raw("12345678901234567890124"));
+    assert!(raw("12345678901234567890123") < raw("12345678901234567890124"));
+    assert!(raw("0.30000000000000000001") > raw("0.3"));
+    assert_eq!(raw("-0.0"), raw("0e7"));
+    assert_eq!(raw("1.50e1"), Number::from(15u64));
+    assert_eq!(raw("0.1"), n("0.1"));
+    assert!(raw("18446744073709551616") > Number::from(u64::MAX));
+    let options = ParseOptions { raw_numbers: true, ..ParseOptions::default() };
+    let a = parse_with("[12345678901234567890123]", &options).unwrap();
+    let b = parse_with("[12345678901234567890124]", &options).unwrap();
+    assert_ne!(a, b);
+    assert_eq!(diff(&a, &b).as_array().map(Vec::len), Some(1));
+    assert_eq!(diff(&parse("1e400").unwrap(), &parse("2e400").unwrap()).as_array().map(Vec::len), Some(1));
+}
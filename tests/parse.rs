@@ -0,0 +1,88 @@
+use std::io::{self, Read};
+
+use shift_reduce_json::grammar::ElementType;
+use shift_reduce_json::{parse, parse_bytes, parse_reader, parse_with, Error, JsonValue, ParseOptions};
+
+// Hands out its input one byte per read, then fails if `fail` is set.
+struct Trickle<'a> {
+    input: &'a [u8],
+    fail: bool,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.input.split_first() {
+            Some((byte, rest)) if !buf.is_empty() => {
+                buf[0] = *byte;
+                self.input = rest;
+                Ok(1)
+            }
+            _ if self.fail => Err(io::Error::other("disconnected")),
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn nesting_is_limited() {
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+    assert!(parse(&nested(128)).is_ok());
+    assert!(parse(&format!("{{\"a\": {}}}", nested(127))).is_ok());
+    match parse(&nested(20_000)) {
+        Err(Error::TooDeep { span, limit: 128 }) => assert_eq!(span.column, 129),
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(parse(&"{\"a\": ".repeat(200)), Err(Error::TooDeep { .. })));
+
+    let options = ParseOptions { max_depth: 1000, ..ParseOptions::default() };
+    assert!(parse_with(&nested(1000), &options).is_ok());
+    assert!(parse_with(&nested(1001), &options).is_err());
+}
+
+#[test]
+fn bytes_and_readers() {
+    let input = "{\"caf\u{e9}\": [1, \"\u{1F600}\", null]}";
+    let expected = parse(input).unwrap();
+    assert_eq!(parse_bytes(input.as_bytes()).unwrap(), expected);
+    assert_eq!(parse_reader(Trickle { input: input.as_bytes(), fail: false }).unwrap(), expected);
+    assert_eq!(parse_reader(&b" 42 "[..]).unwrap(), JsonValue::Number(42u64.into()));
+    assert_eq!(input.parse::<JsonValue>().unwrap(), expected);
+    assert!(matches!("[1,".parse::<JsonValue>(), Err(Error::ParsingError { .. })));
+
+    match parse_bytes(b"[\"\xff\"]") {
+        Err(Error::InvalidUtf8(err)) => assert_eq!(err.valid_up_to(), 2),
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(parse_reader(Trickle { input: b"[1, 2", fail: true }), Err(Error::Io(_))));
+    assert!(matches!(parse_reader(&b"[1, 2"[..]), Err(Error::ParsingError { .. })));
+}
+
+#[test]
+fn errors_point_at_their_position() {
+    let input = "{\n  \"a\": [1, 2],\n  \"b\": 1 2\n}";
+    let error = parse(input).unwrap_err();
+    match &error {
+        Error::UnexpectedToken { found, span, expected } => {
+            assert_eq!(*found, ElementType::Digits);
+            assert_eq!((span.line, span.column, span.offset), (3, 10, 26));
+            assert_eq!(expected, &[ElementType::ObjectEnd, ElementType::Comma]);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        error.render(input),
+        "error: unexpected number at line 3, column 10, expected one of '}' or ','\n  |\n3 |   \"b\": 1 2\n  |          ^"
+    );
+
+    let input = "[\n\t{\"a\": tru}\n]";
+    assert_eq!(parse(input).unwrap_err().render(input), "error: unrecognized token at line 2, column 8\n  |\n2 | \t{\"a\": tru}\n  | \t      ^");
+
+    let input = "[1,\n";
+    let error = parse(input).unwrap_err();
+    assert!(matches!(&error, Error::ParsingError { span, .. } if (span.line, span.column) == (2, 1)));
+    assert_eq!(
+        error.to_string(),
+        "unexpected end of input at line 2, column 1, expected one of string, null, boolean, '{', '[' or number"
+    );
+}
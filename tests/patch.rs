@@ -0,0 +1,87 @@
+use shift_reduce_json::{apply_patch, merge_patch, parse, JsonValue, PatchError, PointerError};
+
+fn json(input: &str) -> JsonValue {
+    parse(input).unwrap()
+}
+
+fn patched(doc: &str, patch: &str) -> Result<JsonValue, PatchError> {
+    let mut doc = json(doc);
+    apply_patch(&mut doc, &json(patch)).map(|()| doc)
+}
+
+#[test]
+fn rfc_6902_examples() {
+    let cases = [
+        (r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#, r#"{"baz": "qux", "foo": "bar"}"#),
+        (r#"{"foo": ["bar", "baz"]}"#, r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#, r#"{"foo": ["bar", "qux", "baz"]}"#),
+        (r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "remove", "path": "/baz"}]"#, r#"{"foo": "bar"}"#),
+        (r#"{"foo": ["bar", "qux", "baz"]}"#, r#"[{"op": "remove", "path": "/foo/1"}]"#, r#"{"foo": ["bar", "baz"]}"#),
+        (r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#, r#"{"baz": "boo", "foo": "bar"}"#),
+        (
+            r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+            r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+            r#"{"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}"#,
+        ),
+        (r#"{"foo": ["all", "grass", "cows", "eat"]}"#, r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#, r#"{"foo": ["all", "cows", "eat", "grass"]}"#),
+        (r#"{"baz": [{"qux": "hello"}]}"#, r#"[{"op": "test", "path": "/baz/0/qux", "value": "hello"}]"#, r#"{"baz": [{"qux": "hello"}]}"#),
+        (r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#, r#"{"foo": "bar", "child": {"grandchild": {}}}"#),
+        (r#"{"foo": ["bar"]}"#, r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#, r#"{"foo": ["bar", ["abc", "def"]]}"#),
+        (r#"{"/": 9, "~1": 10}"#, r#"[{"op": "test", "path": "/~01", "value": 10}]"#, r#"{"/": 9, "~1": 10}"#),
+        (r#"{"a": {"b": 1}}"#, r#"[{"op": "copy", "from": "/a", "path": "/c"}, {"op": "replace", "path": "", "value": [1]}]"#, "[1]"),
+    ];
+    for (doc, patch, expected) in cases {
+        assert_eq!(patched(doc, patch).unwrap(), json(expected), "{}", patch);
+    }
+}
+
+#[test]
+fn failures_leave_the_document_unchanged() {
+    let mut doc = json(r#"{"a": [1, 2], "b": 1}"#);
+    let patch = json(r#"[{"op": "add", "path": "/c", "value": 3}, {"op": "remove", "path": "/a/2"}]"#);
+    let err = apply_patch(&mut doc, &patch).unwrap_err();
+    assert_eq!(
+        err,
+        PatchError::Pointer { index: 1, error: PointerError::BadIndex { at: "/a".to_string(), token: "2".to_string(), len: 2 } }
+    );
+    assert_eq!(doc, json(r#"{"a": [1, 2], "b": 1}"#));
+
+    assert_eq!(
+        patched(r#"{"baz": "qux"}"#, r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#),
+        Err(PatchError::TestFailed { index: 0, path: "/baz".to_string() })
+    );
+    for patch in [
+        r#"{"op": "add"}"#,
+        r#"[{"op": "add", "path": "/x"}]"#,
+        r#"[{"op": "frobnicate", "path": "/x"}]"#,
+        r#"[{"op": "move", "from": "/a", "path": "/a/b"}]"#,
+        r#"[{"op": "replace", "path": "/missing", "value": 1}]"#,
+    ] {
+        assert!(patched(r#"{"a": {}}"#, patch).is_err(), "{}", patch);
+    }
+}
+
+#[test]
+fn rfc_7396_examples() {
+    let cases = [
+        (r#"{"a": "b"}"#, r#"{"a": "c"}"#, r#"{"a": "c"}"#),
+        (r#"{"a": "b"}"#, r#"{"b": "c"}"#, r#"{"a": "b", "b": "c"}"#),
+        (r#"{"a": "b"}"#, r#"{"a": null}"#, "{}"),
+        (r#"{"a": "b", "b": "c"}"#, r#"{"a": null}"#, r#"{"b": "c"}"#),
+        (r#"{"a": ["b"]}"#, r#"{"a": "c"}"#, r#"{"a": "c"}"#),
+        (r#"{"a": "c"}"#, r#"{"a": ["b"]}"#, r#"{"a": ["b"]}"#),
+        (r#"{"a": {"b": "c"}}"#, r#"{"a": {"b": "d", "c": null}}"#, r#"{"a": {"b": "d"}}"#),
+        (r#"{"a": [{"b": "c"}]}"#, r#"{"a": [1]}"#, r#"{"a": [1]}"#),
+        (r#"["a", "b"]"#, r#"["c", "d"]"#, r#"["c", "d"]"#),
+        (r#"{"a": "b"}"#, r#"["c"]"#, r#"["c"]"#),
+        (r#"{"a": "foo"}"#, "null", "null"),
+        (r#"{"a": "foo"}"#, r#""bar""#, r#""bar""#),
+        (r#"{"e": null}"#, r#"{"a": 1}"#, r#"{"e": null, "a": 1}"#),
+        (r#"[1, 2]"#, r#"{"a": "b", "c": null}"#, r#"{"a": "b"}"#),
+        ("{}", r#"{"a": {"bb": {"ccc": null}}}"#, r#"{"a": {"bb": {}}}"#),
+    ];
+    for (doc, patch, expected) in cases {
+        let mut doc = json(doc);
+        merge_patch(&mut doc, &json(patch));
+        assert_eq!(doc, json(expected), "{}", patch);
+    }
+}
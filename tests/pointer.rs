@@ -0,0 +1,53 @@
+use shift_reduce_json::pointer::tokens;
+use shift_reduce_json::{parse, JsonValue, PointerError};
+
+fn json(input: &str) -> JsonValue {
+    parse(input).unwrap()
+}
+
+#[test]
+fn rfc_6901_examples() {
+    let doc = json(r#"{"foo": ["bar", "baz"], "": 0, "a/b": 1, "c%d": 2, "e^f": 3, "g|h": 4, "i\\j": 5,
+                       "k\"l": 6, " ": 7, "m~n": 8}"#);
+    assert_eq!(doc.pointer("").unwrap(), &doc);
+    assert_eq!(doc.pointer("/foo").unwrap(), &json(r#"["bar", "baz"]"#));
+    assert_eq!(doc.pointer("/foo/0").unwrap(), &json(r#""bar""#));
+    for (pointer, n) in [("/", 0), ("/a~1b", 1), ("/c%d", 2), ("/e^f", 3), ("/g|h", 4), ("/i\\j", 5), ("/k\"l", 6), ("/ ", 7), ("/m~0n", 8)] {
+        assert_eq!(doc.pointer(pointer).unwrap(), &json(&n.to_string()), "{}", pointer);
+    }
+    assert_eq!(tokens("/~01").unwrap(), vec!["~1"]);
+    assert!(matches!(tokens("a"), Err(PointerError::Syntax(_))));
+    assert!(matches!(tokens("/~2"), Err(PointerError::Syntax(_))));
+}
+
+#[test]
+fn mutation() {
+    let mut doc = json(r#"{"a": [1, 2], "b": {"c": true}}"#);
+    *doc.pointer_mut("/b/c").unwrap() = json("false");
+    assert_eq!(doc.pointer_insert("/a/-", json("3")).unwrap(), None);
+    assert_eq!(doc.pointer_insert("/a/0", json("0")).unwrap(), None);
+    assert_eq!(doc.pointer_insert("/b/c", json("null")).unwrap(), Some(json("false")));
+    assert_eq!(doc.pointer_insert("/b/d", json("[]")).unwrap(), None);
+    assert_eq!(doc.pointer_remove("/a/1").unwrap(), json("1"));
+    assert_eq!(doc, json(r#"{"a": [0, 2, 3], "b": {"c": null, "d": []}}"#));
+
+    assert_eq!(doc.pointer_insert("", json("1")).unwrap(), Some(json(r#"{"a": [0, 2, 3], "b": {"c": null, "d": []}}"#)));
+    assert_eq!(doc.pointer_remove(""), Err(PointerError::RemoveRoot));
+}
+
+#[test]
+fn errors() {
+    let mut doc = json(r#"{"a": [1, {"b": 2}]}"#);
+    assert_eq!(doc.pointer("/x"), Err(PointerError::MissingKey { at: String::new(), key: "x".to_string() }));
+    assert_eq!(
+        doc.pointer("/a/01"),
+        Err(PointerError::BadIndex { at: "/a".to_string(), token: "01".to_string(), len: 2 })
+    );
+    assert!(matches!(doc.pointer("/a/-"), Err(PointerError::BadIndex { .. })));
+    assert_eq!(doc.pointer_insert("/a/3", json("0")).unwrap_err().to_string(), r#"no index "3" in the array of length 2 at "/a""#);
+    assert_eq!(
+        doc.pointer_remove("/a/0/x").unwrap_err(),
+        PointerError::NotAContainer { at: "/a/0".to_string(), found: "number" }
+    );
+    assert_eq!(doc.pointer_remove("/a/1/c").unwrap_err().to_string(), r#"no member "c" in the object at "/a/1""#);
+}
@@ -0,0 +1,60 @@
+use shift_reduce_json::{parse, Event, ParseOptions, PushParser};
+
+fn push_parse(input: &str, chunk: usize) -> Result<String, String> {
+    let mut parser = PushParser::new();
+    for bytes in input.as_bytes().chunks(chunk) {
+        parser.feed(bytes).map_err(|e| e.to_string())?;
+    }
+    parser.finish().map_err(|e| e.to_string())?;
+    Ok(parser.next_value().unwrap().to_string())
+}
+
+#[test]
+fn any_chunking_matches_parse() {
+    let inputs = [
+        r#"{"name": "café 😀", "n": [0, -12.5e+3, 7], "ok": true, "none": null}"#,
+        "12",
+        "[1, 2",
+        r#"{"a": tru}"#,
+        "[] []",
+    ];
+    for input in inputs {
+        let expected = parse(input).map(|v| v.to_string()).map_err(|e| e.to_string());
+        for chunk in 1..=input.len() {
+            assert_eq!(push_parse(input, chunk), expected, "{:?} in chunks of {}", input, chunk);
+        }
+    }
+}
+
+#[test]
+fn value_is_available_before_finish() {
+    let mut parser = PushParser::new();
+    parser.feed(br#"{"a": [1"#).unwrap();
+    assert!(parser.next_value().is_none());
+    parser.feed(b"]} ").unwrap();
+    assert_eq!(parser.next_value().unwrap().to_string(), r#"{"a":[1]}"#);
+}
+
+#[test]
+fn events_as_input_arrives() {
+    let mut parser = PushParser::with_events(ParseOptions::default());
+    parser.feed(br#"["ab"#).unwrap();
+    assert_eq!(parser.next_event(), Some(Event::StartArray));
+    assert_eq!(parser.next_event(), None);
+    parser.feed(br#"c"]"#).unwrap();
+    assert!(matches!(parser.next_event(), Some(Event::Scalar(_))));
+    assert_eq!(parser.next_event(), Some(Event::EndArray));
+    parser.finish().unwrap();
+}
+
+#[test]
+fn long_tokens_fed_byte_by_byte() {
+    // Each byte carries the string on from where the last one left it, so
+    // this stays linear in the length of the string.
+    let string = r#"ab\"c\\éé😀😀\n"#.repeat(5000);
+    let number = format!("-{}.5e+10", "1234567890".repeat(5000));
+    for input in [format!(r#"["{}", {}]"#, string, number), format!(r#""{}""#, string), number] {
+        let expected = parse(&input).unwrap().to_string();
+        assert_eq!(push_parse(&input, 1).unwrap(), expected);
+    }
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "serde")]
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use shift_reduce_json::de::{from_str, from_value};
+use shift_reduce_json::ser::to_value;
+use shift_reduce_json::{parse, parse_with, to_string, JsonValue, ParseOptions};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Record<'a> {
+    id: u64,
+    name: &'a str,
+    note: String,
+    score: f64,
+    tags: Vec<String>,
+    parent: Option<i64>,
+    kind: Kind,
+    #[serde(default)]
+    extra: HashMap<String, bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+enum Kind {
+    Plain,
+    Sized(u32),
+    Shape { w: u8, h: u8 },
+}
+
+#[test]
+fn derive_deserialize() {
+    let input = r#"{"id": 7, "name": "seven", "note": "a\nb", "score": -1.5e1,
+                    "tags": ["x", "y"], "parent": null, "kind": {"Shape": {"w": 2, "h": 3}}}"#;
+    let record: Record = from_str(input).unwrap();
+    assert_eq!(
+        record,
+        Record {
+            id: 7,
+            name: "seven",
+            note: "a\nb".to_string(),
+            score: -15.0,
+            tags: vec!["x".to_string(), "y".to_string()],
+            parent: None,
+            kind: Kind::Shape { w: 2, h: 3 },
+            extra: HashMap::new(),
+        }
+    );
+}
+
+#[test]
+fn enums_and_values() {
+    assert_eq!(from_str::<Vec<Kind>>(r#"["Plain", {"Sized": 4}]"#).unwrap(), vec![Kind::Plain, Kind::Sized(4)]);
+    assert_eq!(from_value::<(u8, String)>(parse(r#"[1, "one"]"#).unwrap()).unwrap(), (1, "one".to_string()));
+}
+
+#[test]
+fn errors() {
+    assert!(from_str::<u8>("256").unwrap_err().to_string().contains("256"));
+    assert!(from_str::<Vec<u8>>("[1, 2").is_err());
+    assert!(from_str::<Kind>(r#""Round""#).unwrap_err().to_string().contains("Round"));
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Point {
+    x: i32,
+    y: Option<f64>,
+    kinds: Vec<Kind>,
+    labels: HashMap<u8, String>,
+}
+
+#[test]
+fn to_value_and_back() {
+    let point = Point {
+        x: -3,
+        y: Some(0.5),
+        kinds: vec![Kind::Plain, Kind::Sized(4), Kind::Shape { w: 1, h: 2 }],
+        labels: HashMap::from([(1, "one".to_string())]),
+    };
+    let value = to_value(&point).unwrap();
+    assert_eq!(
+        value,
+        parse(r#"{"x": -3, "y": 0.5, "kinds": ["Plain", {"Sized": 4}, {"Shape": {"w": 1, "h": 2}}], "labels": {"1": "one"}}"#)
+            .unwrap()
+    );
+    assert_eq!(from_value::<Point>(value).unwrap(), point);
+
+    assert_eq!(to_value(&f64::NAN).unwrap(), JsonValue::Null);
+    assert_eq!(to_value(&HashMap::from([(vec![1], 2)])).unwrap_err().to_string(), "object key must be a string");
+}
+
+#[test]
+fn json_value_through_serde() {
+    let input = r#"{"a": [1, -2, 3.5, true, null], "b": {"c": "d"}}"#;
+    let value: JsonValue = from_str(input).unwrap();
+    assert_eq!(value, parse(input).unwrap());
+    assert_eq!(to_value(&value).unwrap(), value);
+    assert_eq!(to_string(&to_value(&value).unwrap()), r#"{"a":[1,-2,3.5,true,null],"b":{"c":"d"}}"#);
+}
+
+#[test]
+fn raw_numbers_keep_their_digits() {
+    let value: JsonValue = from_str("[1e400, -1e400]").unwrap();
+    assert_eq!(to_string(&value), "[1e400,-1e400]");
+
+    let raw = ParseOptions { raw_numbers: true, ..ParseOptions::default() };
+    let value = from_value::<JsonValue>(parse_with("[12345678901234567890123, 0.50]", &raw).unwrap()).unwrap();
+    assert_eq!(to_string(&value), "[12345678901234567890123,0.50]");
+    assert_eq!(to_string(&to_value(&value).unwrap()), "[12345678901234567890123,0.50]");
+    assert!(to_value(&value).unwrap().as_array().unwrap()[0].as_number().unwrap().is_raw());
+    let value = parse_with(r#"{"x": 3, "y": 0.50, "kinds": [], "labels": {}}"#, &raw).unwrap();
+    let point = from_value::<Point>(value).unwrap();
+    assert_eq!((point.x, point.y), (3, Some(0.5)));
+}
@@ -0,0 +1,38 @@
+use shift_reduce_json::stream::{Documents, Lines, RecordError};
+use shift_reduce_json::JsonValue;
+
+fn summarize(results: impl Iterator<Item = Result<JsonValue, RecordError>>) -> Vec<String> {
+    results
+        .map(|result| match result {
+            Ok(value) => value.to_string(),
+            Err(err) => format!("error on line {}", err.line),
+        })
+        .collect()
+}
+
+#[test]
+fn one_document_per_line() {
+    let input = "{\"a\": 1}\n\n[1, 2\n\"x\"\r\n{\"b\": tru}\n7";
+    assert_eq!(
+        summarize(Lines::new(input.as_bytes())),
+        vec![r#"{"a":1}"#, "error on line 3", r#""x""#, "error on line 5", "7"]
+    );
+}
+
+#[test]
+fn concatenated_documents() {
+    let input = "1 2 [3][4]{\"a\": 5}\"s\"\ntrue";
+    assert_eq!(
+        summarize(Documents::new(input.as_bytes())),
+        vec!["1", "2", "[3]", "[4]", r#"{"a":5}"#, r#""s""#, "true"]
+    );
+}
+
+#[test]
+fn reading_resumes_after_a_bad_document() {
+    let input = "{\"a\": 1\n{\n  \"b\": [2, ?]\n}\n[3]\n";
+    assert_eq!(
+        summarize(Documents::new(input.as_bytes())),
+        vec!["error on line 1", "error on line 2", "[3]"]
+    );
+}
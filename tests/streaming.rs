@@ -0,0 +1,34 @@
+use std::io::BufReader;
+
+use shift_reduce_json::lexer::Lexer;
+use shift_reduce_json::{parse, parse_buf_reader, ParseOptions};
+
+const DOCUMENT: &str = r#"{"name": "café 😀 é", "n": [0, -12.5e+3, 7], "ok": true, "none": null}"#;
+
+#[test]
+fn tokens_split_across_buffers() {
+    let expected = parse(DOCUMENT).unwrap();
+    for capacity in 1..8 {
+        let reader = BufReader::with_capacity(capacity, DOCUMENT.as_bytes());
+        assert_eq!(parse_buf_reader(reader, &ParseOptions::default()).unwrap(), expected, "capacity {}", capacity);
+    }
+}
+
+#[test]
+fn errors_match_whole_input_lexing() {
+    for input in [r#"{"a": 01}"#, r#"["abc"#, "[tru]", r#"["\ud800"]"#] {
+        let expected = parse(input).unwrap_err().to_string();
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let err = parse_buf_reader(reader, &ParseOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+}
+
+#[test]
+fn lexer_stops_after_an_error() {
+    let mut lexer = Lexer::new("[1, ?, 2]".as_bytes());
+    let results: Vec<_> = lexer.by_ref().collect();
+    assert!(results.last().unwrap().is_err());
+    assert_eq!(results.len(), 4);
+    assert!(lexer.next().is_none());
+}
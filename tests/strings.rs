@@ -0,0 +1,72 @@
+use shift_reduce_json::lexer::{lex_with, TokenError, TokenValue};
+use shift_reduce_json::event::parse_events_with;
+use shift_reduce_json::{parse, parse_with, Error, Event, JsonValue, LoneSurrogates, ParseOptions, PushParser};
+
+fn string(input: &str) -> Result<String, Error> {
+    match parse(input)? {
+        JsonValue::String(s) => Ok(s),
+        other => panic!("{:?}", other),
+    }
+}
+
+fn options(lone_surrogates: LoneSurrogates) -> ParseOptions {
+    ParseOptions { lone_surrogates, ..ParseOptions::default() }
+}
+
+#[test]
+fn escapes_are_decoded() {
+    assert_eq!(string(r#""\ud83d\ude00""#).unwrap(), "😀");
+    assert_eq!(string(r#""a\/b\bc\fd\"\\\n\r\té""#).unwrap(), "a/b\u{8}c\u{c}d\"\\\n\r\té");
+
+    for input in ["\"a\u{1}b\"", "\"a\nb\"", "\"\t\""] {
+        let error = string(input).unwrap_err();
+        assert!(matches!(&error, Error::LexingError(TokenError::StringLexFailure(msg, _)) if msg == "unescaped control character"), "{}", error);
+    }
+
+    let error = string("\"ab\\qc\"").unwrap_err();
+    assert_eq!(error.to_string(), "invalid string at line 1, column 4: invalid escape sequence \\q");
+    assert_eq!(error.span().unwrap().offset, 3);
+    let error = string(r#""\u12x4""#).unwrap_err();
+    assert_eq!(error.span().unwrap().column, 2);
+}
+
+#[test]
+fn lone_surrogates_follow_the_policy() {
+    for (input, column) in [(r#"["\ud83d"]"#, 3), (r#"["\ude00 "]"#, 3), (r#"["a\ud83dA"]"#, 4)] {
+        let error = parse_with(input, &options(LoneSurrogates::Error)).unwrap_err();
+        assert_eq!(error.to_string(), format!("invalid string at line 1, column {}: lone UTF-16 surrogate", column));
+    }
+
+    let replace = options(LoneSurrogates::Replace);
+    assert_eq!(parse_with(r#""\ud83d""#, &replace).unwrap(), JsonValue::String("\u{fffd}".to_string()));
+    assert_eq!(parse_with(r#""\ude00x""#, &replace).unwrap(), JsonValue::String("\u{fffd}x".to_string()));
+    assert_eq!(parse_with(r#""\ud83dA""#, &replace).unwrap(), JsonValue::String("\u{fffd}A".to_string()));
+
+    let preserve = options(LoneSurrogates::Preserve);
+    let wtf8 = |input: &str| lex_with(input, &preserve).unwrap().remove(0).value;
+    assert_eq!(wtf8(r#""\ud83d""#), TokenValue::Wtf8(vec![0xED, 0xA0, 0xBD]));
+    assert_eq!(wtf8(r#""x\ude00""#), TokenValue::Wtf8(vec![b'x', 0xED, 0xB8, 0x80]));
+    assert_eq!(wtf8(r#""😀""#), TokenValue::String("😀".to_string()));
+    assert!(parse_with(r#""\ud83d""#, &preserve).unwrap_err().to_string().contains("cannot be stored in a JsonValue"));
+
+    let input = r#"{"k\udc00": ["\ud83d", "\ud83d\ude00"]}"#;
+    let mut events = Vec::new();
+    parse_events_with(input, &preserve, |event| events.push(event)).unwrap();
+    let expected = [
+        Event::StartObject,
+        Event::Wtf8Key(vec![b'k', 0xED, 0xB0, 0x80]),
+        Event::StartArray,
+        Event::Wtf8String(vec![0xED, 0xA0, 0xBD]),
+        Event::Scalar(JsonValue::String("😀".to_string())),
+        Event::EndArray,
+        Event::EndObject,
+    ];
+    assert_eq!(events, expected);
+
+    let mut parser = PushParser::with_events(preserve);
+    for chunk in input.as_bytes().chunks(3) {
+        parser.feed(chunk).unwrap();
+    }
+    parser.finish().unwrap();
+    assert_eq!(std::iter::from_fn(|| parser.next_event()).collect::<Vec<_>>(), expected);
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use shift_reduce_json::validator::{Draft, SchemaError, ValidationError, ValidatorOptions};
+use shift_reduce_json::{parse, JsonValue, Validator};
+
+fn json(input: &str) -> JsonValue {
+    parse(input).unwrap()
+}
+
+fn errors(validator: &Validator, instance: &str) -> Vec<(String, String)> {
+    match validator.validate(&json(instance)) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.into_iter().map(|e: ValidationError| (e.instance_path, e.schema_path)).collect(),
+    }
+}
+
+#[test]
+fn reports_every_error_with_its_paths() {
+    let schema = json(
+        r#"{
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string", "pattern": "^[a-z]+$"}, "uniqueItems": true},
+                "price": {"multipleOf": 0.01, "exclusiveMaximum": 1000}
+            },
+            "additionalProperties": false
+        }"#,
+    );
+    let validator = Validator::new(&schema).unwrap();
+
+    assert!(validator.is_valid(&json(r#"{"id": 3, "name": "a", "tags": ["x", "yz"], "price": 19.99}"#)));
+    assert!(validator.is_valid(&json(r#"{"id": 3.0, "name": "a"}"#)));
+    assert_eq!(
+        errors(&validator, r#"{"id": 0, "tags": ["ok", "Bad", "ok", 7], "price": 1000, "extra/field": null}"#),
+        [
+            ("/id", "/properties/id/minimum"),
+            ("/tags/1", "/properties/tags/items/pattern"),
+            ("/tags/3", "/properties/tags/items/type"),
+            ("/tags", "/properties/tags/uniqueItems"),
+            ("/price", "/properties/price/exclusiveMaximum"),
+            ("/extra~1field", "/additionalProperties"),
+            ("", "/required"),
+        ]
+        .map(|(i, s)| (i.to_string(), s.to_string()))
+    );
+
+    let error = &validator.validate(&json(r#"{"id": "7", "name": "a"}"#)).unwrap_err()[0];
+    assert_eq!(error.to_string(), "/id: expected integer, found string");
+
+    let error = Validator::new(&json(r#"{"properties": {"a": {"minLength": -1}}}"#)).err().unwrap();
+    assert_eq!(error, SchemaError { location: "#/properties/a".to_string(), message: "minLength must be a non-negative integer".to_string() });
+}
+
+#[test]
+fn resolves_local_and_external_references() {
+    let tree = json(
+        r##"{
+            "$id": "https://example.com/tree.json",
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": {"$ref": "types.json#/$defs/positive"},
+                        "children": {"type": "array", "items": {"$ref": "#/$defs/node"}},
+                        "owner": {"$ref": "people/person.json"}
+                    }
+                }
+            },
+            "$ref": "#/$defs/node"
+        }"##,
+    );
+    let mut documents = HashMap::new();
+    documents.insert("https://example.com/types.json".to_string(), json(r#"{"$defs": {"positive": {"type": "number", "exclusiveMinimum": 0}}}"#));
+    documents.insert(
+        "https://example.com/people/person.json".to_string(),
+        json(r##"{"type": "object", "properties": {"name": {"$ref": "#name"}}, "$defs": {"n": {"$anchor": "name", "type": "string"}}}"##),
+    );
+
+    assert!(Validator::new(&tree).err().unwrap().message.contains("https://example.com/types.json"));
+    let options = ValidatorOptions { resolver: Some(&documents), ..ValidatorOptions::default() };
+    let validator = Validator::with_options(&tree, &options).unwrap();
+    assert!(validator.is_valid(&json(r#"{"value": 1, "children": [{"value": 2, "children": []}], "owner": {"name": "x"}}"#)));
+    assert_eq!(
+        errors(&validator, r#"{"children": [{"value": -2}], "owner": {"name": 5}}"#),
+        [
+            ("/children/0/value", "/$ref/properties/children/items/$ref/properties/value/$ref/exclusiveMinimum"),
+            ("/owner/name", "/$ref/properties/owner/$ref/properties/name/$ref/type"),
+        ]
+        .map(|(i, s)| (i.to_string(), s.to_string()))
+    );
+
+    // In draft-07, `$ref` overrides the keywords beside it.
+    let draft7 = json(
+        r##"{
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "definitions": {"pair": {"type": "array", "items": [{"type": "string"}, {"type": "integer"}], "additionalItems": false}},
+            "properties": {"pair": {"$ref": "#/definitions/pair", "maxItems": 0}},
+            "dependencies": {"a": ["b"]}
+        }"##,
+    );
+    let validator = Validator::new(&draft7).unwrap();
+    assert!(validator.is_valid(&json(r#"{"pair": ["x", 1]}"#)));
+    assert_eq!(
+        errors(&validator, r#"{"pair": ["x", 1, 2], "a": 1}"#),
+        [("/pair/2", "/properties/pair/$ref/additionalItems"), ("", "/dependencies")].map(|(i, s)| (i.to_string(), s.to_string()))
+    );
+    let options = ValidatorOptions { draft: Draft::V7, ..ValidatorOptions::default() };
+    assert!(Validator::with_options(&json(r#"{"items": [true], "additionalItems": false}"#), &options).unwrap().is_valid(&json("[1]")));
+}
+
+#[test]
+fn applicators_and_unevaluated_members() {
+    let schema = json(
+        r#"{
+            "oneOf": [
+                {"properties": {"kind": {"const": "circle"}, "radius": {"type": "number"}}, "required": ["kind", "radius"]},
+                {"properties": {"kind": {"const": "square"}, "side": {"type": "number"}}, "required": ["kind", "side"]}
+            ],
+            "if": {"properties": {"kind": {"const": "circle"}}},
+            "then": {"properties": {"label": {"maxLength": 3}}},
+            "else": {"not": {"required": ["label"]}},
+            "unevaluatedProperties": false
+        }"#,
+    );
+    let validator = Validator::new(&schema).unwrap();
+    assert!(validator.is_valid(&json(r#"{"kind": "circle", "radius": 1, "label": "ok"}"#)));
+    assert!(validator.is_valid(&json(r#"{"kind": "square", "side": 2}"#)));
+    assert_eq!(
+        errors(&validator, r#"{"kind": "square", "side": 2, "label": "x", "radius": 1}"#),
+        [("", "/else/not"), ("/label", "/unevaluatedProperties"), ("/radius", "/unevaluatedProperties")]
+            .map(|(i, s)| (i.to_string(), s.to_string()))
+    );
+    assert_eq!(errors(&validator, r#"{"kind": "circle", "radius": 1, "label": "long"}"#), [("/label".to_string(), "/then/properties/label/maxLength".to_string())]);
+    assert_eq!(errors(&validator, r#"{"kind": "oval"}"#)[0], (String::new(), "/oneOf".to_string()));
+
+    let contains = Validator::new(&json(r#"{"prefixItems": [{"type": "string"}], "contains": {"type": "integer"}, "minContains": 2, "unevaluatedItems": false}"#)).unwrap();
+    assert!(contains.is_valid(&json(r#"["a", 1, 2]"#)));
+    assert_eq!(errors(&contains, r#"["a", 1, null]"#), [("".to_string(), "/contains".to_string()), ("/2".to_string(), "/unevaluatedItems".to_string())]);
+}
+
+#[test]
+fn patterns() {
+    let validator = Validator::new(&json(r#"{"pattern": "^a*$"}"#)).unwrap();
+    assert!(validator.is_valid(&JsonValue::String("a".repeat(100_000))));
+    assert!(!validator.is_valid(&JsonValue::String(format!("{}b", "a".repeat(100_000)))));
+    let validator = Validator::new(&json(r#"{"pattern": "(a|a)*c"}"#)).unwrap();
+    assert!(!validator.is_valid(&JsonValue::String("a".repeat(100_000))));
+
+    let schema = json(r#"{"patternProperties": {"^x-": {"type": "string"}, "\\d$": true}, "additionalProperties": false}"#);
+    let validator = Validator::new(&schema).unwrap();
+    assert!(validator.is_valid(&json(r#"{"x-a": "1", "n2": null}"#)));
+    assert_eq!(
+        errors(&validator, r#"{"x-a": 1, "y": 2, "x-3": 3}"#),
+        [("/x-a", "/patternProperties/^x-/type"), ("/x-3", "/patternProperties/^x-/type"), ("/y", "/additionalProperties")]
+            .map(|(i, s)| (i.to_string(), s.to_string()))
+    );
+}